@@ -0,0 +1,29 @@
+use advent_of_code_2019::intcode_computer;
+use advent_of_code_2019::intcode_computer::cfg::ControlFlowGraph;
+use std::env;
+use std::process;
+
+fn main() {
+    let path = env::args()
+        .nth(1)
+        .expect("Usage: cfg <path to intcode image> [entry address]");
+    let entry = match env::args().nth(2) {
+        Some(a) => match a.parse() {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("invalid entry address {}: {}", a, e);
+                process::exit(1);
+            }
+        },
+        None => 0,
+    };
+    let image = match intcode_computer::load(&path) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("cannot load {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    print!("{}", ControlFlowGraph::build(&image, entry).to_dot(&image));
+}
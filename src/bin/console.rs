@@ -0,0 +1,80 @@
+use advent_of_code_2019::intcode_computer;
+use advent_of_code_2019::intcode_computer::{Event, Program, Word};
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    if args.len() < 2 {
+        eprintln!("Usage: console <path to intcode image> [--ascii]");
+        process::exit(2);
+    }
+    let ascii = args.iter().skip(2).any(|a| a == "--ascii");
+    let image = match intcode_computer::load(&args[1]) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("cannot load {}: {}", args[1], e);
+            process::exit(1);
+        }
+    };
+    let mut program = Program::new(image, None);
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        match program.run_until_event() {
+            Ok(Event::Output(data)) => print_output(data, ascii),
+            Ok(Event::NeedInput) => match read_input(&mut lines, ascii) {
+                Some(inputs) => inputs.into_iter().for_each(|i| program.push_input(i)),
+                None => {
+                    eprintln!("input closed; waiting for input at {}", program.instruction_ptr());
+                    process::exit(1);
+                }
+            },
+            Ok(Event::Halted) => break,
+            Ok(Event::Watchpoint(_)) => (),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+    io::stdout().flush().unwrap();
+    eprintln!(
+        "halted at {} after {} instructions",
+        program.instruction_ptr(),
+        program.executed()
+    );
+}
+
+/// Prints an output as a character in ASCII mode when it is one, else as a number.
+fn print_output(data: Word, ascii: bool) {
+    if ascii && (0..128).contains(&data) {
+        print!("{}", data as u8 as char);
+    } else {
+        println!("{}", data);
+    }
+}
+
+/// Prompts for and reads the next input line: a single integer, or in ASCII
+/// mode a line of text sent as character codes ending with a newline.
+/// Returns `None` once stdin is closed.
+fn read_input<B: BufRead>(lines: &mut io::Lines<B>, ascii: bool) -> Option<Vec<Word>> {
+    loop {
+        if !ascii {
+            print!("> ");
+        }
+        io::stdout().flush().unwrap();
+        let line = lines.next()?.unwrap();
+        if ascii {
+            let mut codes = line.bytes().map(|b| b as Word).collect::<Vec<Word>>();
+            codes.push(10);
+            return Some(codes);
+        }
+        match line.trim().parse::<Word>() {
+            Ok(value) => return Some(vec![value]),
+            Err(_) => eprintln!("expected an integer, got {:?}", line.trim()),
+        }
+    }
+}
@@ -11,7 +11,7 @@ fn module_fuel() -> i32 {
     let mut fuel_required = 0;
     for mass in module_masses {
         let module_fuel = mass / 3 - 2;
-        fuel_required = fuel_required + accrue_fuel(module_fuel);
+        fuel_required += accrue_fuel(module_fuel);
     }
     fuel_required
 }
@@ -19,7 +19,7 @@ fn module_fuel() -> i32 {
 fn accrue_fuel(mut unaccounted_fuel: i32) -> i32 {
     let mut fuel_required = 0;
     while unaccounted_fuel > 0 {
-        fuel_required = fuel_required + unaccounted_fuel;
+        fuel_required += unaccounted_fuel;
         unaccounted_fuel = unaccounted_fuel / 3 - 2;
     }
     fuel_required
@@ -1,15 +1,10 @@
 use advent_of_code_2019::intcode_computer;
-use std::fs;
+use advent_of_code_2019::intcode_computer::Word;
 
 fn main() {
-    let mut input = fs::read_to_string("resources/day2.input").unwrap();
-    input.pop();
-    let instructions = input
-        .split(",")
-        .map(|s| s.parse::<i32>().unwrap())
-        .collect::<Vec<i32>>();
+    let instructions = intcode_computer::load("resources/day2.input").unwrap();
 
-    const PROGRAM_OUTPUT: i32 = 19690720;
+    const PROGRAM_OUTPUT: Word = 19690720;
 
     'outer: for i in 0..100 {
         for j in 0..100 {
@@ -17,7 +12,7 @@ fn main() {
             clone.instructions[1] = i;
             clone.instructions[2] = j;
 
-            intcode_computer::process(&mut clone);
+            intcode_computer::process(&mut clone).unwrap();
             if i == 12 && j == 2 {
                 println!("program output at 1202 : {}", clone.instructions[0]);
             }
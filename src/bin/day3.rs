@@ -26,7 +26,7 @@ impl LineSegment {
 fn process_input(input: String) -> Vec<Vec<LineSegment>> {
     let input = input
         .split("\n")
-        .map(|s| String::from(s))
+        .map(String::from)
         .collect::<Vec<String>>();
 
     input
@@ -92,11 +92,11 @@ fn main() {
 }
 
 fn smallest_wirelength_intersection(set1: &Vec<LineSegment>, set2: &Vec<LineSegment>) -> i32 {
-    let mut smallest_wirelength_intersection = i32::max_value();
+    let mut smallest_wirelength_intersection = i32::MAX;
     for line1 in set1 {
         for line2 in set2 {
-            if let Some(point) = intersect_lines(&line1, &line2) {
-                let wirelength = get_total_wirelength(&line1, &line2, &point);
+            if let Some(point) = intersect_lines(line1, line2) {
+                let wirelength = get_total_wirelength(line1, line2, &point);
 
                 if smallest_wirelength_intersection > wirelength && (point.x != 0 || point.y != 0) {
                     smallest_wirelength_intersection = wirelength;
@@ -129,11 +129,11 @@ fn get_total_wirelength(wire1: &LineSegment, wire2: &LineSegment, intersection:
 }
 
 fn _manhattan_point(set1: &Vec<LineSegment>, set2: &Vec<LineSegment>) -> Point {
-    let mut manhattan_distance = i32::max_value();
+    let mut manhattan_distance = i32::MAX;
     let mut manhattan = Point { x: 0, y: 0 };
     for line1 in set1 {
         for line2 in set2 {
-            if let Some(point) = intersect_lines(&line1, &line2) {
+            if let Some(point) = intersect_lines(line1, line2) {
                 if manhattan_distance > point.x.abs() + point.y.abs()
                     && (point.x != 0 || point.y != 0)
                 {
@@ -164,7 +164,7 @@ fn is_vertical(line: &LineSegment) -> bool {
     line.start.x == line.end.x
 }
 
-fn sort_points<'a>(line1: &'a LineSegment) -> (&'a Point, &'a Point) {
+fn sort_points(line1: &LineSegment) -> (&Point, &Point) {
     if line1.start.x < line1.end.x || line1.start.y < line1.end.y {
         (&line1.start, &line1.end)
     } else {
@@ -2,7 +2,7 @@
 fn re(x: i32, k: i32) -> i32 {
     let mut result = 0;
     for i in 1..x + 1 {
-        result = result + if k == 0 { i } else { re(i, k - 1) };
+        result += if k == 0 { i } else { re(i, k - 1) };
     }
     result
 }
@@ -14,8 +14,8 @@ fn no_dups(n: i32, r: i32) -> i32 {
     }
     let (mut nr, mut dr) = (1, 1);
     for i in 0..r {
-        dr = dr * (r - i);
-        nr = nr * (n - i);
+        dr *= r - i;
+        nr *= n - i;
     }
     nr / dr
 }
@@ -35,14 +35,14 @@ fn get_first_password(start: i32) -> i32 {
             position = i;
             multiplier = mul;
         }
-        i = i + 1;
-        mul = mul * 10;
-        t = t / 10;
+        i += 1;
+        mul *= 10;
+        t /= 10;
     }
     let mut init = start / multiplier;
     while position > 0 {
         init = init * 10 + max;
-        position = position - 1;
+        position -= 1;
     }
     init
 }
@@ -58,8 +58,8 @@ fn split_repeating(n: i32) -> (i32, i32) {
     while t % 10 == lsd {
         sec = sec * 10 + lsd;
         adder = adder * 10 + 1;
-        mul = mul * 10;
-        t = t / 10;
+        mul *= 10;
+        t /= 10;
     }
     if t != 0 {
         t = (t + 1) * mul + ((t + 1) % 10) * adder;
@@ -71,8 +71,8 @@ fn password_count_for_repeating(mut t: i32) -> i32 {
     let x = t % 10;
     let mut count = 0;
     while t > 0 {
-        t = t / 10;
-        count = count + 1;
+        t /= 10;
+        count += 1;
     }
     re(10 - x, count - 2) - no_dups(10 - x, count)
 }
@@ -1,28 +1,22 @@
 use advent_of_code_2019::intcode_computer;
-use advent_of_code_2019::intcode_computer::IO;
+use advent_of_code_2019::intcode_computer::{Word, IO};
 use std::cell::RefCell;
-use std::fs;
 use std::rc::Rc;
 
 fn main() {
-    let mut input = fs::read_to_string("resources/day5.input").unwrap();
-    input.pop();
-    let instructions = input
-        .split(",")
-        .map(|s| s.parse::<i32>().unwrap())
-        .collect::<Vec<i32>>();
+    let instructions = intcode_computer::load("resources/day5.input").unwrap();
 
     #[derive(Clone)]
     struct InOutput {
-        output: Rc<RefCell<i32>>,
-    };
+        output: Rc<RefCell<Word>>,
+    }
 
     impl IO for InOutput {
-        fn read(&mut self) -> i32 {
-            5
+        fn read(&mut self) -> Option<Word> {
+            Some(5)
         }
 
-        fn write(&mut self, data: i32) {
+        fn write(&mut self, data: Word) {
             self.output.replace(data);
         }
     }
@@ -33,7 +27,7 @@ fn main() {
     let io: Option<Box<dyn IO>> = Some(Box::new(InOutput { output }));
 
     let mut clone = intcode_computer::Program::new(instructions.clone(), io);
-    intcode_computer::process(&mut clone);
+    intcode_computer::process(&mut clone).unwrap();
 
     println!("Output: {:?}", out_c);
 }
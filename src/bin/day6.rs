@@ -6,7 +6,7 @@ fn gen_graph(input: String) -> (Vec<Vec<usize>>, HashMap<String, usize>) {
         .split("\n")
         .map(|s| {
             s.split(")")
-                .map(|z| String::from(z))
+                .map(String::from)
                 .collect::<Vec<String>>()
         })
         .fold(
@@ -14,14 +14,14 @@ fn gen_graph(input: String) -> (Vec<Vec<usize>>, HashMap<String, usize>) {
             |(mut orbiter_list, mut objects), list| {
                 let c = String::from(&list[0]);
                 let o = String::from(&list[1]);
-                if !objects.contains_key(&c) {
+                objects.entry(c).or_insert_with(|| {
                     orbiter_list.push(Vec::new());
-                    objects.insert(c, orbiter_list.len() - 1);
-                }
-                if !objects.contains_key(&o) {
+                    orbiter_list.len() - 1
+                });
+                objects.entry(o).or_insert_with(|| {
                     orbiter_list.push(Vec::new());
-                    objects.insert(o, orbiter_list.len() - 1);
-                }
+                    orbiter_list.len() - 1
+                });
                 orbiter_list[*objects.get(&list[0]).unwrap()].push(*objects.get(&list[1]).unwrap());
                 (orbiter_list, objects)
             },
@@ -46,7 +46,7 @@ fn main() {
     println!("Total transfers : {}", total_transfers);
 }
 
-enum moves {
+enum Moves {
     None,
     You(i32),
     San(i32),
@@ -60,44 +60,44 @@ fn count_orbital_transfers(adj_list: &Vec<Vec<usize>>, you: usize, san: usize) -
         visited: &mut Vec<bool>,
         you: usize,
         san: usize,
-    ) -> moves {
+    ) -> Moves {
         let list = &adj_list[start];
         if start == you {
             println!("YOU at {}", start);
-            return moves::You(0);
+            return Moves::You(0);
         }
         if start == san {
             println!("SAN at {}", start);
-            return moves::San(0);
+            return Moves::San(0);
         }
         let mut m = Vec::new();
         for &v in list {
             let x = count_orbital_transfers_rec(adj_list, v, visited, you, san);
             match x {
-                moves::You(a) => {
+                Moves::You(a) => {
                     println!("Y{} at {}", a, start);
                     m.push(x);
                 }
-                moves::San(a) => {
+                Moves::San(a) => {
                     println!("S{} at {}", a, start);
                     m.push(x);
                 }
-                moves::Transfers(a) => {
+                Moves::Transfers(a) => {
                     println!("T{} at {}", a, start);
                     return x;
                 }
-                moves::None => (),
+                Moves::None => (),
             }
             if m.len() == 2 {
                 println!("Found S & Y at {}", start);
-                return moves::Transfers(
+                return Moves::Transfers(
                     (match m.pop().unwrap() {
-                        moves::You(t) => t,
-                        moves::San(t) => t,
+                        Moves::You(t) => t,
+                        Moves::San(t) => t,
                         _ => 0,
                     }) + (match m.pop().unwrap() {
-                        moves::You(t) => t,
-                        moves::San(t) => t,
+                        Moves::You(t) => t,
+                        Moves::San(t) => t,
                         _ => 0,
                     }),
                 );
@@ -106,21 +106,22 @@ fn count_orbital_transfers(adj_list: &Vec<Vec<usize>>, you: usize, san: usize) -
         visited[start] = true;
         if let Some(x) = m.pop() {
             match x {
-                moves::San(a) => moves::San(a + 1),
-                moves::You(a) => moves::You(a + 1),
-                _ => moves::None,
+                Moves::San(a) => Moves::San(a + 1),
+                Moves::You(a) => Moves::You(a + 1),
+                _ => Moves::None,
             }
         } else {
-            moves::None
+            Moves::None
         }
     }
 
     let mut visited = vec![false; adj_list.len()];
     for i in 0..adj_list.len() {
         if !visited[i] {
-            match count_orbital_transfers_rec(adj_list, i, &mut visited, you, san) {
-                moves::Transfers(x) => return x,
-                _ => (),
+            if let Moves::Transfers(x) =
+                count_orbital_transfers_rec(adj_list, i, &mut visited, you, san)
+            {
+                return x;
             }
         }
     }
@@ -139,12 +140,12 @@ fn count_connections(adj_list: &Vec<Vec<usize>>) -> i32 {
         for &v in list {
             if !visited[v] {
                 let (n, t) = count_connections_rec(adj_list, v, visited);
-                nodes_under = nodes_under + n;
+                nodes_under += n;
                 total_edges = total_edges + t + n;
             } else {
                 let (n, _) = count_connections_rec(adj_list, v, visited);
-                nodes_under = nodes_under + n;
-                total_edges = total_edges + n;
+                nodes_under += n;
+                total_edges += n;
             }
         }
         visited[start] = true;
@@ -154,7 +155,7 @@ fn count_connections(adj_list: &Vec<Vec<usize>>) -> i32 {
     let mut total_edges = 0;
     for i in 0..adj_list.len() {
         if !visited[i] {
-            total_edges = total_edges + count_connections_rec(adj_list, i, &mut visited).1;
+            total_edges += count_connections_rec(adj_list, i, &mut visited).1;
         }
     }
     total_edges
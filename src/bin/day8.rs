@@ -35,7 +35,7 @@ fn main() {
 }
 
 fn validate_layers(layers: &Vec<Vec<u32>>) -> u32 {
-    let mut fewest_zeros = u32::max_value();
+    let mut fewest_zeros = u32::MAX;
     let mut product = 0;
 
     for layer in layers {
@@ -44,9 +44,9 @@ fn validate_layers(layers: &Vec<Vec<u32>>) -> u32 {
         let mut twos = 0;
         for i in layer {
             match i {
-                0 => zeros = zeros + 1,
-                1 => ones = ones + 1,
-                2 => twos = twos + 1,
+                0 => zeros += 1,
+                1 => ones += 1,
+                2 => twos += 1,
                 _ => panic!("Invalid digit"),
             }
         }
@@ -68,13 +68,13 @@ fn generate_image(layers: &Vec<Vec<u32>>) -> Vec<u32> {
     image
 }
 
-fn merge_layers(first: &mut Vec<u32>, second: &Vec<u32>) -> bool {
+fn merge_layers(first: &mut [u32], second: &[u32]) -> bool {
     let mut opaques = 0;
     for i in 0..first.len() {
         if first[i] == 2 {
             first[i] = second[i];
         } else {
-            opaques = opaques + 1;
+            opaques += 1;
         }
     }
     opaques == first.len()
@@ -0,0 +1,19 @@
+use advent_of_code_2019::intcode_computer;
+use advent_of_code_2019::intcode_computer::disassembler;
+use std::env;
+use std::process;
+
+fn main() {
+    let path = env::args()
+        .nth(1)
+        .expect("Usage: disassemble <path to intcode image>");
+    let image = match intcode_computer::load(&path) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("cannot load {}: {}", path, e);
+            process::exit(1);
+        }
+    };
+
+    println!("{}", disassembler::disassemble(&image));
+}
@@ -0,0 +1,30 @@
+use advent_of_code_2019::intcode_computer;
+use advent_of_code_2019::intcode_computer::transpiler;
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+    let args = env::args().collect::<Vec<String>>();
+    if args.len() < 2 {
+        panic!("Usage: transpile <path to intcode image> [output .rs file]");
+    }
+    let image = match intcode_computer::load(&args[1]) {
+        Ok(image) => image,
+        Err(e) => {
+            eprintln!("cannot load {}: {}", args[1], e);
+            process::exit(1);
+        }
+    };
+    let source = transpiler::transpile(&image);
+
+    match args.get(2) {
+        Some(path) => {
+            if let Err(e) = fs::write(path, source) {
+                eprintln!("cannot write {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", source),
+    }
+}
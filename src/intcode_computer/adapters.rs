@@ -0,0 +1,257 @@
+use super::{Word, IO};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::mpsc::{Receiver, Sender};
+
+/// Lets boxed IO, as taken by `Program::new`, be wrapped by other adapters.
+impl<T: IO + ?Sized> IO for Box<T> {
+    fn read(&mut self) -> Option<Word> {
+        (**self).read()
+    }
+
+    fn write(&mut self, o: Word) {
+        (**self).write(o)
+    }
+}
+
+/// Reads from an input queue and appends writes to an output list. Clones
+/// share both, so one clone can be attached to a program while the caller
+/// feeds inputs and collects outputs through another.
+#[derive(Debug, Clone, Default)]
+pub struct QueueIO {
+    inputs: Rc<RefCell<VecDeque<Word>>>,
+    outputs: Rc<RefCell<Vec<Word>>>,
+}
+
+impl QueueIO {
+    pub fn new(inputs: Vec<Word>) -> Self {
+        QueueIO {
+            inputs: Rc::new(RefCell::new(inputs.into())),
+            outputs: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    pub fn push_input(&self, value: Word) {
+        self.inputs.borrow_mut().push_back(value);
+    }
+
+    /// Outputs written so far.
+    pub fn outputs(&self) -> Vec<Word> {
+        self.outputs.borrow().clone()
+    }
+
+    /// Removes and returns the outputs written so far.
+    pub fn take_outputs(&self) -> Vec<Word> {
+        self.outputs.replace(Vec::new())
+    }
+}
+
+impl IO for QueueIO {
+    fn read(&mut self) -> Option<Word> {
+        self.inputs.borrow_mut().pop_front()
+    }
+
+    fn write(&mut self, o: Word) {
+        self.outputs.borrow_mut().push(o);
+    }
+}
+
+/// Blocks on a channel for input and sends outputs down another, for programs
+/// running on their own threads. Reads fail once every sender is gone.
+pub struct ChannelIO {
+    input: Receiver<Word>,
+    output: Sender<Word>,
+}
+
+impl ChannelIO {
+    pub fn new(input: Receiver<Word>, output: Sender<Word>) -> Self {
+        ChannelIO { input, output }
+    }
+}
+
+impl IO for ChannelIO {
+    fn read(&mut self) -> Option<Word> {
+        self.input.recv().ok()
+    }
+
+    /// Outputs sent after the receiver has hung up are dropped.
+    fn write(&mut self, o: Word) {
+        let _ = self.output.send(o);
+    }
+}
+
+/// Delegates reads and writes to closures.
+pub struct FnIO<R, W> {
+    read: R,
+    write: W,
+}
+
+impl<R: FnMut() -> Option<Word>, W: FnMut(Word)> FnIO<R, W> {
+    pub fn new(read: R, write: W) -> Self {
+        FnIO { read, write }
+    }
+}
+
+impl<R: FnMut() -> Option<Word>, W: FnMut(Word)> IO for FnIO<R, W> {
+    fn read(&mut self) -> Option<Word> {
+        (self.read)()
+    }
+
+    fn write(&mut self, o: Word) {
+        (self.write)(o)
+    }
+}
+
+/// Supplies a fixed sequence of inputs, then defers to `inner`, which also
+/// receives every write.
+pub struct ScriptIO<T> {
+    script: VecDeque<Word>,
+    inner: T,
+}
+
+impl<T: IO> ScriptIO<T> {
+    pub fn new(script: Vec<Word>, inner: T) -> Self {
+        ScriptIO {
+            script: script.into(),
+            inner,
+        }
+    }
+}
+
+impl<T: IO> IO for ScriptIO<T> {
+    fn read(&mut self) -> Option<Word> {
+        self.script.pop_front().or_else(|| self.inner.read())
+    }
+
+    fn write(&mut self, o: Word) {
+        self.inner.write(o)
+    }
+}
+
+/// A value that crossed the `IO` boundary. A read of `None` is an attempt made
+/// after the input ran out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    Read(Option<Word>),
+    Write(Word),
+}
+
+/// Passes everything through to `inner` while logging it in order.
+pub struct Recorder<T> {
+    inner: T,
+    transcript: Rc<RefCell<Vec<Transfer>>>,
+}
+
+impl<T: IO> Recorder<T> {
+    pub fn new(inner: T) -> Self {
+        Recorder {
+            inner,
+            transcript: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Handle on the log, which stays readable after the recorder has been
+    /// attached to a program.
+    pub fn transcript(&self) -> Rc<RefCell<Vec<Transfer>>> {
+        self.transcript.clone()
+    }
+}
+
+impl<T: IO> IO for Recorder<T> {
+    fn read(&mut self) -> Option<Word> {
+        let value = self.inner.read();
+        self.transcript.borrow_mut().push(Transfer::Read(value));
+        value
+    }
+
+    fn write(&mut self, o: Word) {
+        self.transcript.borrow_mut().push(Transfer::Write(o));
+        self.inner.write(o)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{process, IntcodeError, Program};
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// Echoes inputs until it reads 0: `IN -> [20]; OUT [20]; JT [20], #0; HLT`.
+    fn echo() -> Vec<Word> {
+        vec![3, 20, 4, 20, 1005, 20, 0, 99]
+    }
+
+    #[test]
+    fn queue_io_clones_share_inputs_and_outputs() {
+        let io = QueueIO::new(vec![4]);
+        let mut program = Program::new(echo(), Some(Box::new(io.clone())));
+        assert_eq!(process(&mut program), Err(IntcodeError::InputExhausted));
+        assert_eq!(io.outputs(), vec![4]);
+
+        io.push_input(5);
+        io.push_input(0);
+        assert_eq!(process(&mut program), Ok(()));
+        assert_eq!(io.take_outputs(), vec![4, 5, 0]);
+        assert!(io.outputs().is_empty());
+    }
+
+    #[test]
+    fn channel_io_runs_a_program_on_another_thread() {
+        let (input, program_input) = mpsc::channel();
+        let (program_output, output) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let io = ChannelIO::new(program_input, program_output);
+            process(&mut Program::new(echo(), Some(Box::new(io))))
+        });
+        input.send(6).unwrap();
+        assert_eq!(output.recv(), Ok(6));
+        drop(input);
+        assert_eq!(handle.join().unwrap(), Err(IntcodeError::InputExhausted));
+        assert!(output.recv().is_err());
+    }
+
+    #[test]
+    fn channel_io_drops_outputs_nobody_receives() {
+        let (input, program_input) = mpsc::channel();
+        let (program_output, output) = mpsc::channel();
+        drop(output);
+        input.send(7).unwrap();
+        input.send(0).unwrap();
+        let io = ChannelIO::new(program_input, program_output);
+        assert_eq!(process(&mut Program::new(echo(), Some(Box::new(io)))), Ok(()));
+    }
+
+    #[test]
+    fn fn_io_calls_the_closures() {
+        let outputs = Rc::new(RefCell::new(Vec::new()));
+        let sink = outputs.clone();
+        let mut inputs = vec![0, 8];
+        let io = FnIO::new(move || inputs.pop(), move |o| sink.borrow_mut().push(o));
+        assert_eq!(process(&mut Program::new(echo(), Some(Box::new(io)))), Ok(()));
+        assert_eq!(*outputs.borrow(), vec![8, 0]);
+    }
+
+    #[test]
+    fn script_io_falls_back_to_the_inner_io() {
+        let inner = QueueIO::new(vec![3, 0]);
+        let io = ScriptIO::new(vec![1, 2], inner.clone());
+        assert_eq!(process(&mut Program::new(echo(), Some(Box::new(io)))), Ok(()));
+        assert_eq!(inner.outputs(), vec![1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn recorder_logs_every_transfer_in_order() {
+        let inner = QueueIO::new(vec![9]);
+        let recorder = Recorder::new(inner.clone());
+        let transcript = recorder.transcript();
+        let mut program = Program::new(echo(), Some(Box::new(recorder)));
+        assert_eq!(process(&mut program), Err(IntcodeError::InputExhausted));
+        assert_eq!(
+            *transcript.borrow(),
+            vec![Transfer::Read(Some(9)), Transfer::Write(9), Transfer::Read(None)]
+        );
+        assert_eq!(inner.outputs(), vec![9]);
+    }
+}
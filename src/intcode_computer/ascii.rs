@@ -0,0 +1,221 @@
+use super::{Event, IntcodeError, Program, Word, IO};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+#[derive(Debug, Default)]
+struct Buffers {
+    inputs: VecDeque<Word>,
+    partial: String,
+    lines: VecDeque<String>,
+    values: Vec<Word>,
+}
+
+/// Talks to a program in ASCII: strings are sent as character codes and
+/// outputs are buffered into lines of text. Outputs outside the ASCII range
+/// are kept apart as numeric values, usually a final answer.
+///
+/// Clones share their buffers, so one clone can be attached to a program while
+/// the caller sends commands and reads lines through another.
+#[derive(Debug, Clone, Default)]
+pub struct AsciiIO {
+    buffers: Rc<RefCell<Buffers>>,
+}
+
+impl AsciiIO {
+    pub fn new() -> Self {
+        AsciiIO::default()
+    }
+
+    /// Queues the character codes of `text` as input.
+    pub fn send(&self, text: &str) {
+        let mut buffers = self.buffers.borrow_mut();
+        buffers.inputs.extend(text.bytes().map(|b| b as Word));
+    }
+
+    /// Queues `command` followed by a newline.
+    pub fn send_line(&self, command: &str) {
+        self.send(command);
+        self.send("\n");
+    }
+
+    /// Removes and returns the oldest complete line of output, without its newline.
+    pub fn next_line(&self) -> Option<String> {
+        self.buffers.borrow_mut().lines.pop_front()
+    }
+
+    /// Iterates over complete lines of output, consuming them.
+    pub fn lines(&self) -> Lines {
+        Lines { io: self.clone() }
+    }
+
+    /// Text output since the last newline, such as a prompt.
+    pub fn partial_line(&self) -> String {
+        self.buffers.borrow().partial.clone()
+    }
+
+    /// Non-ASCII values output so far.
+    pub fn values(&self) -> Vec<Word> {
+        self.buffers.borrow().values.clone()
+    }
+
+    /// The last non-ASCII value output, which ASCII programs use for their answer.
+    pub fn answer(&self) -> Option<Word> {
+        self.buffers.borrow().values.last().copied()
+    }
+}
+
+impl IO for AsciiIO {
+    fn read(&mut self) -> Option<Word> {
+        self.buffers.borrow_mut().inputs.pop_front()
+    }
+
+    fn write(&mut self, o: Word) {
+        let mut buffers = self.buffers.borrow_mut();
+        match o {
+            10 => {
+                let line = std::mem::take(&mut buffers.partial);
+                buffers.lines.push_back(line);
+            }
+            0..=127 => buffers.partial.push(o as u8 as char),
+            _ => buffers.values.push(o),
+        }
+    }
+}
+
+pub struct Lines {
+    io: AsciiIO,
+}
+
+impl Iterator for Lines {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.io.next_line()
+    }
+}
+
+/// A program driven through an `AsciiIO`, for scripted sessions: send a
+/// command, run until the program wants more input, read the lines it printed.
+pub struct AsciiSession {
+    program: Program,
+    io: AsciiIO,
+}
+
+impl AsciiSession {
+    pub fn new(image: Vec<Word>) -> Self {
+        let io = AsciiIO::new();
+        let program = Program::new(image, Some(Box::new(io.clone())));
+        AsciiSession { program, io }
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn io(&self) -> &AsciiIO {
+        &self.io
+    }
+
+    pub fn send_line(&mut self, command: &str) {
+        self.io.send_line(command);
+    }
+
+    /// Runs until the program blocks on input or halts, returning whether it halted.
+    pub fn run(&mut self) -> Result<bool, IntcodeError> {
+        loop {
+            match self.program.run_until_event()? {
+                Event::Output(data) => self.io.write(data),
+                Event::NeedInput => return Ok(false),
+                Event::Halted => return Ok(true),
+                Event::Watchpoint(_) => (),
+            }
+        }
+    }
+
+    /// Sends `command`, runs until the program wants input again or halts and
+    /// returns the complete lines it printed meanwhile.
+    pub fn command(&mut self, command: &str) -> Result<Vec<String>, IntcodeError> {
+        self.send_line(command);
+        self.run()?;
+        Ok(self.io.lines().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::assembler::assemble;
+    use super::*;
+
+    /// Prompts with `> ` and echoes each line followed by 1000 plus its length
+    /// including the newline. A single-character line ends the session with -1.
+    fn echo() -> Vec<Word> {
+        assemble(
+            "
+            start:  OUT #62
+                    OUT #32
+            loop:   IN -> [char]
+                    OUT [char]
+                    ADD [count], #1 -> [count]
+                    EQ [char], #10 -> [flag]
+                    JF [flag], #loop
+                    ADD [count], #1000 -> [flag]
+                    OUT [flag]
+                    EQ [count], #2 -> [flag]
+                    JT [flag], #done
+                    ADD #0, #0 -> [count]
+                    JT #1, #start
+            done:   OUT #-1
+                    HLT
+            char:   DATA 0
+            count:  DATA 0
+            flag:   DATA 0",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn buffers_lines_and_keeps_values_apart() {
+        let mut io = AsciiIO::new();
+        for o in [104, 105, 10, 10, 500, 111, 107, -3] {
+            io.write(o);
+        }
+        assert_eq!(io.partial_line(), "ok");
+        assert_eq!(io.values(), vec![500, -3]);
+        assert_eq!(io.answer(), Some(-3));
+        assert_eq!(io.next_line(), Some(String::from("hi")));
+        assert_eq!(io.lines().collect::<Vec<String>>(), vec![String::new()]);
+        assert_eq!(io.next_line(), None);
+
+        io.write(10);
+        assert_eq!(io.partial_line(), "");
+        assert_eq!(io.clone().next_line(), Some(String::from("ok")));
+    }
+
+    #[test]
+    fn sends_text_as_character_codes() {
+        let mut io = AsciiIO::new();
+        io.send("ab");
+        io.send_line("c");
+        let inputs = std::iter::from_fn(|| io.read()).collect::<Vec<Word>>();
+        assert_eq!(inputs, vec![97, 98, 99, 10]);
+    }
+
+    #[test]
+    fn session_commands_return_the_lines_printed() {
+        let mut session = AsciiSession::new(echo());
+        assert_eq!(session.run(), Ok(false));
+        assert_eq!(session.io().partial_line(), "> ");
+        assert_eq!(session.io().next_line(), None);
+
+        assert_eq!(session.command("hello"), Ok(vec![String::from("> hello")]));
+        assert_eq!(session.io().partial_line(), "> ");
+        assert_eq!(session.io().values(), vec![1006]);
+        assert!(!session.program().is_halted());
+
+        assert_eq!(session.command("q"), Ok(vec![String::from("> q")]));
+        assert!(session.program().is_halted());
+        assert_eq!(session.io().values(), vec![1006, 1002, -1]);
+        assert_eq!(session.io().answer(), Some(-1));
+    }
+}
@@ -0,0 +1,326 @@
+use super::{OpCodes, Word};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// A problem found while assembling, tagged with its 1-based source line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblyError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AssemblyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AssemblyError {}
+
+/// Assembles Intcode source into a program image ready for `Program::new`.
+///
+/// Each line holds an optional `label:` followed by an instruction or a
+/// `DATA` directive; `;` starts a comment. Instructions use the disassembler's
+/// mnemonics and operand syntax: `[x]` position, `#x` immediate and `[rb+x]`
+/// relative, with the write target given either as the last operand or after
+/// `->`. Operand values and `DATA` values are numbers, labels or sums of them,
+/// such as `#loop` or `[table+2]`.
+///
+/// ```text
+/// loop:   IN -> [value]
+///         OUT [value]
+///         JT #1, #loop
+/// value:  DATA 0
+/// ```
+pub fn assemble(source: &str) -> Result<Vec<Word>, AssemblyError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = 0;
+
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut text = text.split(';').next().unwrap().trim();
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+            if !is_identifier(label) {
+                return Err(error(line, format!("invalid label '{}'", label)));
+            }
+            if labels.insert(label.to_string(), address as Word).is_some() {
+                return Err(error(line, format!("duplicate label '{}'", label)));
+            }
+            text = text[colon + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let statement = parse_statement(text).map_err(|message| error(line, message))?;
+        address += statement.size();
+        statements.push((line, statement));
+    }
+
+    let mut image = Vec::with_capacity(address);
+    for (line, statement) in statements {
+        statement
+            .emit(&labels, &mut image)
+            .map_err(|message| error(line, message))?;
+    }
+    Ok(image)
+}
+
+fn error(line: usize, message: String) -> AssemblyError {
+    AssemblyError { line, message }
+}
+
+enum Statement {
+    Instruction(OpCodes, Vec<Operand>),
+    Data(Vec<Expression>),
+}
+
+struct Operand {
+    mode: Word,
+    value: Expression,
+}
+
+/// Signed sum of numbers and labels.
+struct Expression(Vec<(Word, Term)>);
+
+enum Term {
+    Number(Word),
+    Label(String),
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self {
+            Statement::Instruction(_, operands) => 1 + operands.len(),
+            Statement::Data(values) => values.len(),
+        }
+    }
+
+    fn emit(&self, labels: &HashMap<String, Word>, image: &mut Vec<Word>) -> Result<(), String> {
+        match self {
+            Statement::Instruction(code, operands) => {
+                let modes = operands
+                    .iter()
+                    .rev()
+                    .fold(0, |modes, operand| modes * 10 + operand.mode);
+                image.push(modes * 100 + code.value());
+                for operand in operands {
+                    image.push(operand.value.resolve(labels)?);
+                }
+            }
+            Statement::Data(values) => {
+                for value in values {
+                    image.push(value.resolve(labels)?);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Expression {
+    fn resolve(&self, labels: &HashMap<String, Word>) -> Result<Word, String> {
+        let mut total: Word = 0;
+        for (sign, term) in &self.0 {
+            let value = match term {
+                Term::Number(n) => *n,
+                Term::Label(label) => *labels
+                    .get(label)
+                    .ok_or_else(|| format!("undefined label '{}'", label))?,
+            };
+            total = sign
+                .checked_mul(value)
+                .and_then(|term| total.checked_add(term))
+                .ok_or_else(|| "value out of range".to_string())?;
+        }
+        Ok(total)
+    }
+}
+
+fn parse_statement(text: &str) -> Result<Statement, String> {
+    let (head, rest) = match text.find(char::is_whitespace) {
+        Some(i) => (&text[..i], text[i..].trim()),
+        None => (text, ""),
+    };
+    let head = head.to_uppercase();
+    let arguments = rest
+        .replace("->", ",")
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect::<Vec<String>>();
+
+    if head == "DATA" {
+        if arguments.is_empty() {
+            return Err("DATA needs at least one value".to_string());
+        }
+        let values = arguments
+            .iter()
+            .map(|a| parse_expression(a))
+            .collect::<Result<Vec<Expression>, String>>()?;
+        return Ok(Statement::Data(values));
+    }
+
+    let code = OpCodes::from_mnemonic(&head).ok_or(format!("unknown mnemonic '{}'", head))?;
+    if arguments.len() != code.param_count() {
+        return Err(format!(
+            "{} takes {} operands, found {}",
+            head,
+            code.param_count(),
+            arguments.len()
+        ));
+    }
+    let operands = arguments
+        .iter()
+        .map(|a| parse_operand(a))
+        .collect::<Result<Vec<Operand>, String>>()?;
+    if code.writes() && operands.last().unwrap().mode == 1 {
+        return Err(format!("{} cannot write to an immediate operand", head));
+    }
+    Ok(Statement::Instruction(code, operands))
+}
+
+fn parse_operand(text: &str) -> Result<Operand, String> {
+    if let Some(value) = text.strip_prefix('#') {
+        return Ok(Operand {
+            mode: 1,
+            value: parse_expression(value)?,
+        });
+    }
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|t| t.strip_suffix(']'))
+        .ok_or(format!("invalid operand '{}'", text))?
+        .trim();
+    match inner.strip_prefix("rb") {
+        Some(offset) if offset.trim().is_empty() => Ok(Operand {
+            mode: 2,
+            value: Expression(vec![(1, Term::Number(0))]),
+        }),
+        Some(offset) if offset.trim_start().starts_with(['+', '-']) => Ok(Operand {
+            mode: 2,
+            value: parse_expression(offset)?,
+        }),
+        _ => Ok(Operand {
+            mode: 0,
+            value: parse_expression(inner)?,
+        }),
+    }
+}
+
+fn parse_expression(text: &str) -> Result<Expression, String> {
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    let mut rest = text.trim();
+    if rest.is_empty() {
+        return Err("missing value".to_string());
+    }
+    loop {
+        if let Some(r) = rest.strip_prefix('-') {
+            sign = -sign;
+            rest = r.trim_start();
+            continue;
+        }
+        if let Some(r) = rest.strip_prefix('+') {
+            rest = r.trim_start();
+            continue;
+        }
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let token = rest[..end].trim();
+        // Numbers are parsed with their sign so that `Word::MIN` fits.
+        let number = if sign < 0 { format!("-{}", token) } else { token.to_string() };
+        if let Ok(n) = number.parse::<Word>() {
+            terms.push((1, Term::Number(n)));
+        } else if !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("value '{}' out of range", number));
+        } else if is_identifier(token) {
+            terms.push((sign, Term::Label(token.to_string())));
+        } else {
+            return Err(format!("invalid value '{}'", text.trim()));
+        }
+        sign = 1;
+        rest = rest[end..].trim_start();
+        if rest.is_empty() {
+            return Ok(Expression(terms));
+        }
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::{self, disassembler};
+
+    /// Reassembles a disassembly listing by stripping its address prefixes.
+    fn reassemble(image: &[Word]) -> Vec<Word> {
+        let source = disassembler::disassemble(image)
+            .lines()
+            .map(|line| line.split_once(": ").unwrap().1)
+            .collect::<Vec<&str>>()
+            .join("\n");
+        assemble(&source).unwrap()
+    }
+
+    #[test]
+    fn assembles_labels_modes_and_data() {
+        let source = "
+            start:  IN -> [value]        ; read
+                    ADD [value], #-3, [rb+2]
+                    OUT [rb-1]
+                    JT #1, #start
+            value:  DATA 7, value+1, end-start
+            end:";
+        let image = assemble(source).unwrap();
+        assert_eq!(
+            image,
+            vec![3, 11, 21001, 11, -3, 2, 204, -1, 1105, 1, 0, 7, 12, 14]
+        );
+    }
+
+    #[test]
+    fn disassembly_round_trips() {
+        let quine = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert_eq!(reassemble(&quine), quine);
+        let extremes = vec![1101, Word::MIN, Word::MAX, 9, 99, Word::MIN, Word::MAX];
+        assert_eq!(reassemble(&extremes), extremes);
+        for day in ["day2", "day5", "day7"] {
+            let image = intcode_computer::load(&format!("resources/{}.input", day)).unwrap();
+            assert_eq!(reassemble(&image), image, "{}", day);
+        }
+    }
+
+    #[test]
+    fn errors_carry_their_line() {
+        let cases = [
+            ("HLT\nJT #1, #nowhere", 2, "undefined label 'nowhere'"),
+            ("a: HLT\na: HLT", 2, "duplicate label 'a'"),
+            ("\n\nADD [1], #2", 3, "ADD takes 3 operands, found 2"),
+            ("IN #4", 1, "IN cannot write to an immediate operand"),
+            ("NOP", 1, "unknown mnemonic 'NOP'"),
+            ("OUT [x", 1, "invalid operand '[x'"),
+            ("DATA", 1, "DATA needs at least one value"),
+            ("DATA 9223372036854775807+1", 1, "value out of range"),
+            ("x: DATA x-9223372036854775808-1", 1, "value out of range"),
+            ("DATA 9223372036854775808", 1, "value '9223372036854775808' out of range"),
+        ];
+        for (source, line, message) in cases {
+            let error = assemble(source).unwrap_err();
+            assert_eq!((error.line, error.message.as_str()), (line, message), "{}", source);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use super::{Instruction, Word};
+
+/// Decoded instructions indexed by address.
+///
+/// Decoding depends only on the op code word, so each entry keeps the word it
+/// was decoded from and is only used while memory still holds that word.
+/// Writes to an address drop its entry as well, so self-modifying code never
+/// runs a stale decoding.
+#[derive(Default)]
+pub(crate) struct DecodeCache {
+    entries: Vec<Option<(Word, Instruction)>>,
+}
+
+impl DecodeCache {
+    pub(crate) fn get(&self, address: usize, word: Word) -> Option<Instruction> {
+        match self.entries.get(address) {
+            Some(Some((cached, instruction))) if *cached == word => Some(*instruction),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn insert(&mut self, instruction: Instruction, word: Word) {
+        let address = instruction.address;
+        if address >= self.entries.len() {
+            self.entries.resize(address + 1, None);
+        }
+        self.entries[address] = Some((word, instruction));
+    }
+
+    pub(crate) fn invalidate(&mut self, address: usize) {
+        if let Some(entry) = self.entries.get_mut(address) {
+            *entry = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Event, Program};
+
+    #[test]
+    fn rewritten_op_codes_are_decoded_again() {
+        // OUT #7; ADD #0, #4 -> [0]; JT #1, #0, which turns the OUT into `OUT [7]`
+        let mut program = Program::new(vec![104, 7, 1101, 0, 4, 0, 1105, 1, 0], None);
+        assert_eq!(program.run_until_event(), Ok(Event::Output(7)));
+        assert_eq!(program.run_until_event(), Ok(Event::Output(1)));
+        assert_eq!(program.instructions[0], 4);
+    }
+}
@@ -0,0 +1,184 @@
+use super::disassembler;
+use super::{Instruction, OpCodes, Word};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Where control goes when a basic block finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    /// Falls through to the next instruction, or skips a jump not taken.
+    Next(usize),
+    /// A jump taken to an immediate-mode target.
+    Jump(usize),
+    /// A jump whose target is read from memory and cannot be resolved statically.
+    Unknown,
+}
+
+/// A run of instructions entered only at `start` and left only after the last one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    pub instructions: Vec<Instruction>,
+    pub edges: Vec<Edge>,
+}
+
+impl BasicBlock {
+    /// Address just past the last instruction of the block.
+    pub fn end(&self) -> usize {
+        let last = self.instructions[self.instructions.len() - 1];
+        last.address + 1 + last.param_modes().len()
+    }
+}
+
+/// Basic blocks reachable from an entry point, keyed by start address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub entry: usize,
+    pub blocks: BTreeMap<usize, BasicBlock>,
+}
+
+impl ControlFlowGraph {
+    /// Builds the graph of code reachable from `entry` in `image`.
+    ///
+    /// Blocks start at the entry, at every resolved jump target and after every
+    /// jump. They end at jumps, halts, the start of another block, or cells that
+    /// do not decode to an instruction.
+    pub fn build(image: &[Word], entry: usize) -> Self {
+        let code = disassembler::reachable(image, entry);
+        let mut leaders = BTreeSet::new();
+        if code.contains_key(&entry) {
+            leaders.insert(entry);
+        }
+        for instruction in code.values().filter(|i| ends_block(i)) {
+            leaders.extend(disassembler::successors(instruction, image));
+        }
+        leaders.retain(|address| code.contains_key(address));
+
+        let mut blocks = BTreeMap::new();
+        for &start in &leaders {
+            let mut instructions = Vec::new();
+            let mut address = start;
+            let edges = loop {
+                let instruction = code[&address];
+                instructions.push(instruction);
+                let next = address + 1 + instruction.param_modes().len();
+                if ends_block(&instruction) {
+                    break edges(&instruction, image);
+                }
+                if leaders.contains(&next) || !code.contains_key(&next) {
+                    break vec![Edge::Next(next)];
+                }
+                address = next;
+            };
+            blocks.insert(
+                start,
+                BasicBlock {
+                    start,
+                    instructions,
+                    edges,
+                },
+            );
+        }
+        ControlFlowGraph { entry, blocks }
+    }
+
+    /// The block containing the instruction at `address`, if any.
+    pub fn block_at(&self, address: usize) -> Option<&BasicBlock> {
+        self.blocks
+            .values()
+            .find(|b| b.instructions.iter().any(|i| i.address == address))
+    }
+
+    /// Renders the graph in Graphviz DOT, labelling each block with its
+    /// disassembly. Unknown edges point to a single dashed `unknown` node, and
+    /// edges to cells that do not decode to a single dashed `invalid` node.
+    pub fn to_dot(&self, image: &[Word]) -> String {
+        let mut out = String::from("digraph cfg {\n    node [shape=box, fontname=monospace];\n");
+        let mut has_unknown = false;
+        let mut has_invalid = false;
+        for block in self.blocks.values() {
+            let label = block
+                .instructions
+                .iter()
+                .map(|i| format!("{}\\l", disassembler::format_instruction(i, image)))
+                .collect::<String>();
+            writeln!(out, "    b{} [label=\"{}\"];", block.start, label).unwrap();
+            for edge in &block.edges {
+                let (target, attributes) = match edge {
+                    Edge::Next(target) => (self.node(*target), ""),
+                    Edge::Jump(target) => (self.node(*target), " [label=\"jump\"]"),
+                    Edge::Unknown => (String::from("unknown"), " [style=dashed]"),
+                };
+                has_unknown |= target == "unknown";
+                has_invalid |= target == "invalid";
+                writeln!(out, "    b{} -> {}{};", block.start, target, attributes).unwrap();
+            }
+        }
+        if has_unknown {
+            out.push_str("    unknown [shape=diamond, style=dashed];\n");
+        }
+        if has_invalid {
+            out.push_str("    invalid [shape=octagon, style=dashed];\n");
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// DOT node for the block starting at `address`, or `invalid` if no
+    /// instruction could be decoded there.
+    fn node(&self, address: usize) -> String {
+        if self.blocks.contains_key(&address) {
+            format!("b{}", address)
+        } else {
+            String::from("invalid")
+        }
+    }
+}
+
+fn ends_block(instruction: &Instruction) -> bool {
+    matches!(
+        instruction.code,
+        OpCodes::JumpIfTrue | OpCodes::JumpIfFalse | OpCodes::Halt
+    )
+}
+
+fn edges(instruction: &Instruction, image: &[Word]) -> Vec<Edge> {
+    if instruction.code == OpCodes::Halt {
+        return Vec::new();
+    }
+    let next = instruction.address + 1 + instruction.param_modes().len();
+    let jump = match disassembler::jump_target(instruction, image) {
+        Some(target) => Edge::Jump(target),
+        None => Edge::Unknown,
+    };
+    vec![Edge::Next(next), jump]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_blocks_at_jumps_and_targets() {
+        // IN -> [12]; JF [12], [15]; ADD [13], [14] -> [13]; OUT [13]; HLT
+        let image = vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
+        let graph = ControlFlowGraph::build(&image, 0);
+        assert_eq!(graph.blocks.keys().copied().collect::<Vec<usize>>(), vec![0, 5]);
+        assert_eq!(graph.blocks[&0].edges, vec![Edge::Next(5), Edge::Unknown]);
+        assert_eq!(graph.blocks[&5].end(), 12);
+        assert!(graph.blocks[&5].edges.is_empty());
+        assert_eq!(graph.block_at(9).map(|b| b.start), Some(5));
+    }
+
+    #[test]
+    fn edges_into_undecodable_cells_go_to_the_invalid_node() {
+        // ADD #1, #1 -> [0]; then the undecodable cell 77
+        let image = vec![1101, 1, 1, 0, 77];
+        let graph = ControlFlowGraph::build(&image, 0);
+        assert_eq!(graph.blocks[&0].edges, vec![Edge::Next(4)]);
+        let dot = graph.to_dot(&image);
+        assert!(dot.contains("    b0 -> invalid;\n"));
+        assert!(dot.contains("    invalid [shape=octagon, style=dashed];\n"));
+        assert!(!dot.contains("b4"));
+    }
+}
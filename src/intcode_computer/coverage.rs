@@ -0,0 +1,178 @@
+use super::disassembler;
+use super::{OpCodes, Step, Word};
+use std::collections::HashMap;
+
+/// Outcomes seen at a branching instruction. Jumps count as taken when they
+/// jump; comparisons count as taken when they store 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Branch {
+    pub taken: u64,
+    pub not_taken: u64,
+}
+
+impl Branch {
+    /// Whether both outcomes have been seen.
+    pub fn is_covered(&self) -> bool {
+        self.taken > 0 && self.not_taken > 0
+    }
+}
+
+/// Executed instructions and branch outcomes gathered by
+/// `Program::enable_coverage`, possibly merged across several runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    pub executed: HashMap<usize, u64>,
+    pub branches: HashMap<usize, Branch>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Coverage::default()
+    }
+
+    pub fn record(&mut self, step: &Step) {
+        let address = step.instruction.address;
+        *self.executed.entry(address).or_insert(0) += 1;
+        let taken = match step.instruction.code {
+            OpCodes::JumpIfTrue | OpCodes::JumpIfFalse => step.jump.is_some(),
+            OpCodes::LessThan | OpCodes::Equals => step.write.is_some_and(|w| w.new == 1),
+            _ => return,
+        };
+        let branch = self.branches.entry(address).or_default();
+        if taken {
+            branch.taken += 1;
+        } else {
+            branch.not_taken += 1;
+        }
+    }
+
+    /// Adds the counts of `other`, typically collected by another run of the
+    /// same image.
+    pub fn merge(&mut self, other: &Coverage) {
+        for (&address, &count) in &other.executed {
+            *self.executed.entry(address).or_insert(0) += count;
+        }
+        for (&address, branch) in &other.branches {
+            let merged = self.branches.entry(address).or_default();
+            merged.taken += branch.taken;
+            merged.not_taken += branch.not_taken;
+        }
+    }
+
+    pub fn is_executed(&self, address: usize) -> bool {
+        self.executed.contains_key(&address)
+    }
+
+    /// Addresses of the instructions in the image's disassembly that never ran.
+    pub fn uncovered(&self, image: &[Word]) -> Vec<usize> {
+        disassembler::sweep(image)
+            .into_iter()
+            .filter(|&(address, instruction)| instruction.is_some() && !self.is_executed(address))
+            .map(|(address, _)| address)
+            .collect()
+    }
+
+    /// Renders the disassembly of `image`, each instruction prefixed with its
+    /// execution count or `-` if it never ran, e.g.
+    /// `    3 0012: JT [101], #0  ; taken 2, not taken 1`.
+    ///
+    /// Branches that only went one way are flagged with `partial`.
+    pub fn listing(&self, image: &[Word]) -> String {
+        let mut lines = Vec::new();
+        for (address, instruction) in disassembler::sweep(image) {
+            let instruction = match instruction {
+                Some(instruction) => instruction,
+                None => {
+                    lines.push(format!("{:>5} {:04}: DATA {}", "", address, image[address]));
+                    continue;
+                }
+            };
+            let count = match self.executed.get(&address) {
+                Some(count) => count.to_string(),
+                None => String::from("-"),
+            };
+            let mut line = format!(
+                "{:>5} {}",
+                count,
+                disassembler::format_instruction(&instruction, image)
+            );
+            if let Some(branch) = self.branches.get(&address) {
+                line.push_str(&format!(
+                    "  ; taken {}, not taken {}",
+                    branch.taken, branch.not_taken
+                ));
+                if !branch.is_covered() {
+                    line.push_str(" partial");
+                }
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Event, Program};
+    use super::*;
+
+    /// `IN -> [20]; LT [20], #5 -> [21]; EQ [20], #0 -> [22]; JT [21], #16;
+    /// JF [22], #16; HLT`, followed by data.
+    fn classify() -> Vec<Word> {
+        let mut image = vec![3, 20, 1007, 20, 5, 21, 1008, 20, 0, 22, 1005, 21, 16];
+        image.extend([1006, 22, 16, 99, 0, 0, 0, 0, 0, 0]);
+        image
+    }
+
+    fn covered_run(input: Word) -> Coverage {
+        let mut program = Program::new(classify(), None);
+        program.enable_coverage();
+        program.push_input(input);
+        assert_eq!(program.run_until_event(), Ok(Event::Halted));
+        program.take_coverage().unwrap()
+    }
+
+    fn branch(taken: u64, not_taken: u64) -> Branch {
+        Branch { taken, not_taken }
+    }
+
+    #[test]
+    fn counts_branch_outcomes() {
+        let coverage = covered_run(3);
+        assert_eq!(coverage.branches[&2], branch(1, 0));
+        assert_eq!(coverage.branches[&6], branch(0, 1));
+        assert_eq!(coverage.branches[&10], branch(1, 0));
+        assert!(!coverage.branches.contains_key(&13));
+        assert_eq!(coverage.uncovered(&classify()), vec![13]);
+
+        let coverage = covered_run(7);
+        assert_eq!(coverage.branches[&2], branch(0, 1));
+        assert_eq!(coverage.branches[&10], branch(0, 1));
+        assert_eq!(coverage.branches[&13], branch(1, 0));
+        assert!(coverage.uncovered(&classify()).is_empty());
+    }
+
+    #[test]
+    fn merge_adds_counts_across_runs() {
+        let mut coverage = covered_run(3);
+        coverage.merge(&covered_run(7));
+        assert_eq!(coverage.executed[&0], 2);
+        assert_eq!(coverage.executed[&13], 1);
+        assert!(coverage.branches[&2].is_covered());
+        assert!(coverage.branches[&10].is_covered());
+        assert_eq!(coverage.branches[&6], branch(0, 2));
+        assert_eq!(coverage.branches[&13], branch(1, 0));
+        let listing = coverage.listing(&classify());
+        assert!(listing.contains("    2 0002: LT [20], #5 -> [21]  ; taken 1, not taken 1\n"));
+    }
+
+    #[test]
+    fn listing_flags_partial_branches() {
+        let listing = covered_run(3).listing(&classify());
+        let lines = listing.lines().collect::<Vec<&str>>();
+        assert_eq!(lines[0], "    1 0000: IN -> [20]");
+        assert_eq!(lines[1], "    1 0002: LT [20], #5 -> [21]  ; taken 1, not taken 0 partial");
+        assert_eq!(lines[4], "    - 0013: JF [22], #16");
+        assert_eq!(lines[6], "      0017: DATA 0");
+    }
+}
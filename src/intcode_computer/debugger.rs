@@ -0,0 +1,199 @@
+use super::history::UndoEntry;
+use super::{Event, IntcodeError, Program, Step, Word};
+
+/// Condition under which `Debugger::run` stops before executing an instruction.
+///
+/// A breakpoint fires when its condition starts to hold, so resuming from a stop
+/// does not immediately stop again at the same breakpoint.
+pub enum Breakpoint {
+    /// Stops when the instruction pointer reaches the address.
+    Address(usize),
+    /// Stops when the memory cell at `address` satisfies `condition`.
+    Memory {
+        address: usize,
+        condition: Box<dyn Fn(Word) -> bool>,
+    },
+}
+
+impl Breakpoint {
+    fn hit(&self, program: &Program) -> bool {
+        match self {
+            Breakpoint::Address(address) => program.instruction_ptr() == *address,
+            Breakpoint::Memory { address, condition } => {
+                condition(program.instructions.get(*address).copied().unwrap_or(0))
+            }
+        }
+    }
+}
+
+/// Why `Debugger::run` handed control back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The breakpoint at this index matched; the instruction at the pointer has not run yet.
+    Breakpoint(usize),
+    Event(Event),
+}
+
+/// Drives a `Program` one instruction at a time, stopping at breakpoints.
+pub struct Debugger {
+    program: Program,
+    breakpoints: Vec<Breakpoint>,
+    held: Vec<bool>,
+}
+
+impl Debugger {
+    pub fn new(program: Program) -> Self {
+        Debugger {
+            program,
+            breakpoints: Vec::new(),
+            held: Vec::new(),
+        }
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn program_mut(&mut self) -> &mut Program {
+        &mut self.program
+    }
+
+    pub fn into_program(self) -> Program {
+        self.program
+    }
+
+    /// Adds a breakpoint and returns its index, as reported by `Stop::Breakpoint`.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.held.push(false);
+        self.breakpoints.len() - 1
+    }
+
+    pub fn break_at(&mut self, address: usize) -> usize {
+        self.add_breakpoint(Breakpoint::Address(address))
+    }
+
+    pub fn break_when<F>(&mut self, address: usize, condition: F) -> usize
+    where
+        F: Fn(Word) -> bool + 'static,
+    {
+        self.add_breakpoint(Breakpoint::Memory {
+            address,
+            condition: Box::new(condition),
+        })
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+        self.held.clear();
+    }
+
+    /// Executes one instruction. Pausing watchpoint hits it causes are reported
+    /// by the next `run`.
+    pub fn step(&mut self) -> Result<Step, IntcodeError> {
+        let step = self.program.step()?;
+        self.check();
+        Ok(step)
+    }
+
+    /// Runs until a breakpoint fires or the program raises an event, including
+    /// hits of pausing watchpoints.
+    pub fn run(&mut self) -> Result<Stop, IntcodeError> {
+        loop {
+            if let Some(event) = self.program.pending_event() {
+                return Ok(Stop::Event(event));
+            }
+            if let Some(index) = self.check() {
+                return Ok(Stop::Breakpoint(index));
+            }
+            if let Some(event) = self.program.step()?.event {
+                self.program.defer_event(event);
+            }
+        }
+    }
+
+    /// Undoes the last executed instruction; needs `Program::enable_history`.
+    pub fn step_back(&mut self) -> Option<UndoEntry> {
+        let entry = self.program.step_back()?;
+        self.check();
+        Some(entry)
+    }
+
+    /// Steps backwards until the most recent write to `address` is undone,
+    /// leaving the pointer on the instruction that made it.
+    pub fn run_back_to_write(&mut self, address: usize) -> Option<UndoEntry> {
+        self.who_wrote(address)?;
+        loop {
+            let entry = self.step_back()?;
+            if entry.write.map(|w| w.address) == Some(address) {
+                return Some(entry);
+            }
+        }
+    }
+
+    /// Address of the instruction that last wrote to the memory cell `address`.
+    pub fn who_wrote(&self, address: usize) -> Option<usize> {
+        let history = self.program.history()?;
+        history.last_write_to(address).map(|e| e.address)
+    }
+
+    /// Re-evaluates every breakpoint, returning the first one that started to hold.
+    fn check(&mut self) -> Option<usize> {
+        let mut fired = None;
+        for (index, breakpoint) in self.breakpoints.iter().enumerate() {
+            let hit = breakpoint.hit(&self.program);
+            if hit && !self.held[index] && fired.is_none() {
+                fired = Some(index);
+            }
+            self.held[index] = hit;
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts [12] up to 3: `ADD [12], #1 -> [12]; EQ [12], #3 -> [13]; JF [13], #0; HLT`.
+    fn counter() -> Program {
+        let image = vec![1001, 12, 1, 12, 1008, 12, 3, 13, 1006, 13, 0, 99, 0, 0];
+        Program::new(image, None)
+    }
+
+    #[test]
+    fn address_breakpoint_stops_on_every_arrival() {
+        let mut debugger = Debugger::new(counter());
+        let breakpoint = debugger.break_at(4);
+        for count in 1..=3 {
+            assert_eq!(debugger.run(), Ok(Stop::Breakpoint(breakpoint)));
+            assert_eq!(debugger.program().instruction_ptr(), 4);
+            assert_eq!(debugger.program().instructions[12], count);
+        }
+        assert_eq!(debugger.run(), Ok(Stop::Event(Event::Halted)));
+    }
+
+    #[test]
+    fn memory_breakpoint_stops_when_condition_starts_to_hold() {
+        let mut debugger = Debugger::new(counter());
+        let breakpoint = debugger.break_when(12, |value| value >= 2);
+        assert_eq!(debugger.run(), Ok(Stop::Breakpoint(breakpoint)));
+        assert_eq!(debugger.program().instructions[12], 2);
+        assert_eq!(debugger.program().instruction_ptr(), 4);
+        assert_eq!(debugger.run(), Ok(Stop::Event(Event::Halted)));
+    }
+
+    #[test]
+    fn who_wrote_and_run_back_to_write_use_the_history() {
+        let mut program = counter();
+        program.enable_history();
+        let mut debugger = Debugger::new(program);
+        assert_eq!(debugger.run(), Ok(Stop::Event(Event::Halted)));
+        assert_eq!(debugger.who_wrote(13), Some(4));
+
+        let entry = debugger.run_back_to_write(12).unwrap();
+        assert_eq!(entry.address, 0);
+        assert_eq!(debugger.program().instructions[12], 2);
+        assert_eq!(debugger.program().instruction_ptr(), 0);
+    }
+}
@@ -0,0 +1,132 @@
+use super::{Instruction, OpCodes, Word};
+use std::collections::BTreeMap;
+
+/// Renders a program image as one annotated line per instruction, e.g.
+/// `0012: ADD [100], #3 -> [101]`.
+///
+/// Parameters are shown as `[n]` in position mode, `#n` in immediate mode and
+/// `[rb+n]` in relative mode. Cells that do not decode to a valid instruction
+/// are emitted as `DATA n`.
+pub fn disassemble(image: &[Word]) -> String {
+    sweep(image)
+        .into_iter()
+        .map(|(address, instruction)| match instruction {
+            Some(instruction) => format_instruction(&instruction, image),
+            None => format!("{:04}: DATA {}", address, image[address]),
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Splits the image into instructions by a linear sweep from address 0. Cells
+/// that do not decode to a valid instruction come back as `None`.
+pub(crate) fn sweep(image: &[Word]) -> Vec<(usize, Option<Instruction>)> {
+    let mut cells = Vec::new();
+    let mut address = 0;
+    while address < image.len() {
+        let instruction = decode(image, address);
+        cells.push((address, instruction));
+        address += instruction.map_or(1, |i| 1 + i.param_modes().len());
+    }
+    cells
+}
+
+/// Decodes the instruction at `address` if it is well formed and fits in the image.
+pub(crate) fn decode(image: &[Word], address: usize) -> Option<Instruction> {
+    let instruction = Instruction::decode(address, image[address]).ok()?;
+    let count = instruction.param_modes().len();
+    if address + count >= image.len() || image[address] / 100 >= (10 as Word).pow(count as u32) {
+        return None;
+    }
+    let valid = instruction.param_modes().iter().enumerate().all(|(i, mode)| match mode {
+        0 | 2 => true,
+        1 => !(instruction.code.writes() && i == count - 1),
+        _ => false,
+    });
+    if valid {
+        Some(instruction)
+    } else {
+        None
+    }
+}
+
+/// Decodes every instruction reachable from `entry` by falling through or by
+/// jumps to immediate-mode targets. Jumps whose target is read from memory are
+/// not followed, and decoding stops at cells that are not valid instructions.
+pub fn reachable(image: &[Word], entry: usize) -> BTreeMap<usize, Instruction> {
+    let mut found = BTreeMap::new();
+    let mut pending = vec![entry];
+    while let Some(address) = pending.pop() {
+        if address >= image.len() || found.contains_key(&address) {
+            continue;
+        }
+        let instruction = match decode(image, address) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+        found.insert(address, instruction);
+        pending.extend(successors(&instruction, image));
+    }
+    found
+}
+
+/// Statically known addresses control can move to after `instruction`.
+pub(crate) fn successors(instruction: &Instruction, image: &[Word]) -> Vec<usize> {
+    let address = instruction.address;
+    let next = address + 1 + instruction.param_modes().len();
+    match instruction.code {
+        OpCodes::Halt => Vec::new(),
+        OpCodes::JumpIfTrue | OpCodes::JumpIfFalse => {
+            let mut targets = vec![next];
+            if let Some(target) = jump_target(instruction, image) {
+                targets.push(target);
+            }
+            targets
+        }
+        _ => vec![next],
+    }
+}
+
+/// Target of a jump instruction when it is given in immediate mode.
+pub(crate) fn jump_target(instruction: &Instruction, image: &[Word]) -> Option<usize> {
+    let target = image[instruction.address + 2];
+    if instruction.param_modes()[1] == 1 && target >= 0 {
+        Some(target as usize)
+    } else {
+        None
+    }
+}
+
+pub(crate) fn format_instruction(instruction: &Instruction, image: &[Word]) -> String {
+    let address = instruction.address;
+    let mut operands = instruction
+        .param_modes()
+        .iter()
+        .enumerate()
+        .map(|(i, &mode)| format_operand(mode, image[address + 1 + i]))
+        .collect::<Vec<String>>();
+    let target = if instruction.code.writes() {
+        operands.pop()
+    } else {
+        None
+    };
+    let mut line = format!("{:04}: {}", address, instruction.code.name());
+    if !operands.is_empty() {
+        line.push(' ');
+        line.push_str(&operands.join(", "));
+    }
+    if let Some(target) = target {
+        line.push_str(" -> ");
+        line.push_str(&target);
+    }
+    line
+}
+
+fn format_operand(mode: Word, parameter: Word) -> String {
+    match mode {
+        0 => format!("[{}]", parameter),
+        1 => format!("#{}", parameter),
+        _ if parameter < 0 => format!("[rb{}]", parameter),
+        _ => format!("[rb+{}]", parameter),
+    }
+}
@@ -0,0 +1,69 @@
+use super::Word;
+use std::error::Error;
+use std::fmt;
+
+/// Everything that can go wrong while running an Intcode program.
+///
+/// Addresses refer to the memory cell holding the offending opcode or parameter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntcodeError {
+    InvalidOpCode { address: usize, code: Word },
+    InvalidParameterMode { address: usize, mode: Word },
+    NegativeAddress { address: usize, target: Word },
+    AddressOutOfRange { target: usize, limit: usize },
+    MissingIO,
+    InputExhausted,
+    ImmediateWriteTarget { address: usize },
+    /// Arithmetic in the instruction at `address` overflowed a `Word`.
+    Overflow { address: usize },
+    /// The instruction budget ran out before the instruction at `address`.
+    InstructionBudgetExceeded { address: usize, budget: u64 },
+    /// The deadline passed before the instruction at `address`.
+    DeadlineExceeded { address: usize },
+    /// The instruction at `address` wrote into code at `target` in strict mode.
+    SelfModifyingCode { address: usize, target: usize },
+    /// `Program::register_op_code` was given a built-in op code, one outside
+    /// `1..100`, or more than 3 parameters.
+    InvalidCustomOpCode { code: Word, param_count: usize },
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntcodeError::InvalidOpCode { address, code } => {
+                write!(f, "invalid op code {} at {}", code, address)
+            }
+            IntcodeError::InvalidParameterMode { address, mode } => {
+                write!(f, "invalid parameter mode {} at {}", mode, address)
+            }
+            IntcodeError::NegativeAddress { address, target } => {
+                write!(f, "negative address {} referenced at {}", target, address)
+            }
+            IntcodeError::AddressOutOfRange { target, limit } => {
+                write!(f, "address {} is beyond the memory limit of {} cells", target, limit)
+            }
+            IntcodeError::MissingIO => write!(f, "program has no IO attached"),
+            IntcodeError::InputExhausted => write!(f, "input exhausted"),
+            IntcodeError::ImmediateWriteTarget { address } => {
+                write!(f, "immediate mode write target at {}", address)
+            }
+            IntcodeError::Overflow { address } => {
+                write!(f, "arithmetic overflow at {}", address)
+            }
+            IntcodeError::InstructionBudgetExceeded { address, budget } => {
+                write!(f, "instruction budget of {} exceeded at {}", budget, address)
+            }
+            IntcodeError::DeadlineExceeded { address } => {
+                write!(f, "deadline exceeded at {}", address)
+            }
+            IntcodeError::SelfModifyingCode { address, target } => {
+                write!(f, "instruction at {} wrote into code at {}", address, target)
+            }
+            IntcodeError::InvalidCustomOpCode { code, param_count } => {
+                write!(f, "cannot register op code {} with {} parameters", code, param_count)
+            }
+        }
+    }
+}
+
+impl Error for IntcodeError {}
@@ -0,0 +1,80 @@
+use super::{Action, IntcodeError, Program, Word};
+use std::collections::HashMap;
+
+/// Executes a custom instruction. It is called with the pointer just past the
+/// op code and the instruction's parameter modes, and reads its parameters
+/// through `Program::parameter` and `Program::location`.
+pub type Handler = Box<dyn FnMut(&mut Program, &[Word]) -> Result<Action, IntcodeError>>;
+
+/// Op codes registered on a program on top of the built-in instruction set.
+#[derive(Default)]
+pub(crate) struct Extensions {
+    op_codes: HashMap<Word, (usize, Option<Handler>)>,
+}
+
+impl Extensions {
+    pub(crate) fn register(&mut self, code: Word, param_count: usize, handler: Handler) {
+        self.op_codes.insert(code, (param_count, Some(handler)));
+    }
+
+    pub(crate) fn param_count(&self, code: Word) -> Option<usize> {
+        self.op_codes.get(&code).map(|(count, _)| *count)
+    }
+
+    /// Removes the handler for `code` while it runs, so it can borrow the program.
+    pub(crate) fn take(&mut self, code: Word) -> Option<Handler> {
+        self.op_codes.get_mut(&code)?.1.take()
+    }
+
+    pub(crate) fn put_back(&mut self, code: Word, handler: Handler) {
+        if let Some(entry) = self.op_codes.get_mut(&code) {
+            entry.1 = Some(handler);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noop() -> Handler {
+        Box::new(|_, _| Ok(Action::Continue))
+    }
+
+    #[test]
+    fn rejects_built_in_and_out_of_range_op_codes() {
+        let mut program = Program::new(vec![99], None);
+        for (code, param_count) in [(1, 0), (99, 0), (0, 0), (100, 0), (-50, 0), (50, 4)] {
+            assert_eq!(
+                program.register_op_code(code, param_count, noop()),
+                Err(IntcodeError::InvalidCustomOpCode { code, param_count })
+            );
+        }
+        assert_eq!(program.register_op_code(50, 3, noop()), Ok(()));
+    }
+
+    #[test]
+    fn registering_again_changes_how_the_op_code_decodes() {
+        // EXT50, first with no parameters and jumping back to itself, then as
+        // `EXT50 #7, [3]` storing its first parameter into the second.
+        let mut program = Program::new(vec![150, 7, 3, 0, 99], None);
+        let jump_back = Box::new(|program: &mut Program, _: &[Word]| {
+            program.jump_to(0)?;
+            Ok(Action::Continue)
+        });
+        program.register_op_code(50, 0, jump_back).unwrap();
+        assert_eq!(program.step().map(|s| s.instruction.param_modes().len()), Ok(0));
+        assert_eq!(program.instruction_ptr(), 0);
+
+        let store = Box::new(|program: &mut Program, modes: &[Word]| {
+            let value = program.parameter(modes[0])?;
+            let location = program.location(modes[1])?;
+            program.store(location, value)?;
+            Ok(Action::Continue)
+        });
+        program.register_op_code(50, 2, store).unwrap();
+        assert_eq!(program.step().map(|s| s.instruction.param_modes().len()), Ok(2));
+        assert_eq!(program.instructions[3], 7);
+        assert_eq!(program.instruction_ptr(), 3);
+    }
+}
@@ -0,0 +1,68 @@
+use super::{Step, Word, Write};
+
+/// State needed to undo one executed instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UndoEntry {
+    /// Address of the instruction, which is where the pointer returns to.
+    pub address: usize,
+    pub relative_base: Word,
+    pub write: Option<Write>,
+    /// Input consumed by the instruction; undoing it queues the input again.
+    pub input: Option<Word>,
+}
+
+/// Undo log of executed instructions, newest last.
+///
+/// Outputs cannot be taken back: stepping backwards over an output and running
+/// forward again produces it a second time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct History {
+    entries: Vec<UndoEntry>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        History::default()
+    }
+
+    pub(crate) fn record(&mut self, step: &Step, relative_base: Word) {
+        self.entries.push(UndoEntry {
+            address: step.instruction.address,
+            relative_base,
+            write: step.write,
+            input: step.input,
+        });
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<UndoEntry> {
+        self.entries.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[UndoEntry] {
+        &self.entries
+    }
+
+    /// The most recent entry that wrote to `address`.
+    pub fn last_write_to(&self, address: usize) -> Option<&UndoEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|e| e.write.map(|w| w.address) == Some(address))
+    }
+
+    /// Every entry that wrote to `address`, oldest first.
+    pub fn writes_to(&self, address: usize) -> Vec<&UndoEntry> {
+        self.entries
+            .iter()
+            .filter(|e| e.write.map(|w| w.address) == Some(address))
+            .collect()
+    }
+}
@@ -1,95 +1,740 @@
 use log::debug;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::ops::Range;
 use std::thread;
+use std::time::Instant;
+
+pub mod adapters;
+pub mod ascii;
+pub mod assembler;
+pub mod cfg;
+pub mod coverage;
+pub mod debugger;
+pub mod disassembler;
+pub mod extensions;
+mod cache;
+mod error;
+pub mod history;
+pub mod network;
+pub mod profiler;
+pub mod scheduler;
+pub mod self_modification;
+pub mod snapshot;
+pub mod topology;
+pub mod trace;
+pub mod transpiler;
+pub mod watch;
+
+use cache::DecodeCache;
+use coverage::Coverage;
+pub use error::IntcodeError;
+use extensions::{Extensions, Handler};
+use history::{History, UndoEntry};
+use profiler::Profile;
+use self_modification::SelfModifications;
+use snapshot::Snapshot;
+use trace::Trace;
+use watch::{Access, WatchAction, WatchHit, Watchpoint, Watchpoints};
+
+/// Machine word of the Intcode computer, used for memory cells, parameters and IO.
+pub type Word = i64;
+
+/// Number of memory cells a program may grow to unless configured otherwise.
+pub const DEFAULT_MEMORY_LIMIT: usize = 1 << 24;
+
+/// Number of instructions executed between checks of a program's deadline.
+pub const DEADLINE_CHECK_INTERVAL: u64 = 1024;
 
 pub trait IO {
-    fn read(&mut self) -> i32;
-    fn write(&mut self, o: i32);
+    /// Returns the next input, or `None` once the input is exhausted.
+    fn read(&mut self) -> Option<Word>;
+    fn write(&mut self, o: Word);
 }
 
 pub struct Program {
-    pub instructions: Vec<i32>,
+    pub instructions: Vec<Word>,
     instruction_ptr: usize,
+    relative_base: Word,
+    memory_limit: usize,
+    executed: u64,
+    instruction_budget: Option<u64>,
+    deadline: Option<Instant>,
+    inputs: VecDeque<Word>,
+    halted: bool,
+    effects: Effects,
+    decoded: DecodeCache,
+    trace: Option<Trace>,
+    history: Option<History>,
+    profile: Option<Profile>,
+    coverage: Option<Coverage>,
+    self_modifications: Option<SelfModifications>,
+    watchpoints: Watchpoints,
+    watch_hits: VecDeque<WatchHit>,
+    /// Event held back until the watchpoint hits of its instruction are reported.
+    deferred_event: Option<Event>,
+    extensions: Extensions,
     io: Option<Box<dyn IO>>,
 }
 
+/// Reason `Program::run_until_event` handed control back to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The program is blocked on an input instruction; push an input and resume.
+    NeedInput,
+    Output(Word),
+    Halted,
+    /// A pausing watchpoint was hit. Only raised by `run_until_event`, after the
+    /// instruction making the access has finished and before any other event
+    /// that instruction raised.
+    Watchpoint(WatchHit),
+}
+
+/// A memory cell overwritten by an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Write {
+    pub address: usize,
+    pub old: Word,
+    pub new: Word,
+}
+
+/// Side effects collected while executing a single instruction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Effects {
+    address: usize,
+    operands: [Word; 3],
+    operand_count: usize,
+    write: Option<Write>,
+    input: Option<Word>,
+    jump: Option<usize>,
+    relative_base: Option<Word>,
+}
+
+impl Effects {
+    fn push_operand(&mut self, value: Word) {
+        self.operands[self.operand_count] = value;
+        self.operand_count += 1;
+    }
+}
+
+/// A single executed instruction together with what it did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub instruction: Instruction,
+    operands: [Word; 3],
+    pub write: Option<Write>,
+    pub input: Option<Word>,
+    /// Target of a taken jump.
+    pub jump: Option<usize>,
+    /// New relative base, if the instruction adjusted it.
+    pub relative_base: Option<Word>,
+    /// Event raised by the instruction, if any. On `NeedInput` the instruction
+    /// did not execute and the pointer still refers to it.
+    pub event: Option<Event>,
+}
+
+impl Step {
+    /// Parameter values after resolving their modes; write targets resolve to
+    /// the address written.
+    pub fn operands(&self) -> &[Word] {
+        &self.operands[..self.instruction.param_modes().len()]
+    }
+
+    pub fn output(&self) -> Option<Word> {
+        match self.event {
+            Some(Event::Output(data)) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a comma separated Intcode image from `path`. Cells that are not
+/// integers are reported as `InvalidData`.
+pub fn load(path: &str) -> io::Result<Vec<Word>> {
+    fs::read_to_string(path)?
+        .trim()
+        .split(',')
+        .map(|s| {
+            s.trim()
+                .parse::<Word>()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+        .collect()
+}
+
 impl Program {
-    pub fn new(instructions: Vec<i32>, io: Option<Box<dyn IO>>) -> Self {
+    pub fn new(instructions: Vec<Word>, io: Option<Box<dyn IO>>) -> Self {
         Program {
             instruction_ptr: 0,
+            relative_base: 0,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            executed: 0,
+            instruction_budget: None,
+            deadline: None,
+            inputs: VecDeque::new(),
+            halted: false,
+            effects: Effects::default(),
+            decoded: DecodeCache::default(),
+            trace: None,
+            history: None,
+            profile: None,
+            coverage: None,
+            self_modifications: None,
+            watchpoints: Watchpoints::default(),
+            watch_hits: VecDeque::new(),
+            deferred_event: None,
+            extensions: Extensions::default(),
             instructions,
             io,
         }
     }
 
-    fn next(&mut self) -> i32 {
-        self.instruction_ptr = self.instruction_ptr + 1;
-        let ins = self.instructions[self.instruction_ptr - 1];
+    /// Rebuilds a program from a snapshot, attaching `io` to it.
+    pub fn restore(snapshot: &Snapshot, io: Option<Box<dyn IO>>) -> Self {
+        let mut program = Program::new(snapshot.memory.clone(), io);
+        program.instruction_ptr = snapshot.instruction_ptr;
+        program.relative_base = snapshot.relative_base;
+        program.memory_limit = snapshot.memory_limit;
+        program.inputs = snapshot.inputs.iter().copied().collect();
+        program.halted = snapshot.halted;
+        program
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            memory: self.instructions.clone(),
+            instruction_ptr: self.instruction_ptr,
+            relative_base: self.relative_base,
+            memory_limit: self.memory_limit,
+            inputs: self.inputs.iter().copied().collect(),
+            halted: self.halted,
+        }
+    }
+
+    /// Caps the number of memory cells the program may address; memory past the
+    /// loaded image grows on demand up to this limit.
+    pub fn set_memory_limit(&mut self, limit: usize) {
+        self.memory_limit = limit;
+    }
+
+    /// Stops execution with `IntcodeError::InstructionBudgetExceeded` once
+    /// `budget` instructions have executed in total.
+    pub fn set_instruction_budget(&mut self, budget: u64) {
+        self.instruction_budget = Some(budget);
+    }
+
+    /// Stops execution with `IntcodeError::DeadlineExceeded` once `deadline`
+    /// has passed. The clock is only consulted every `DEADLINE_CHECK_INTERVAL`
+    /// instructions.
+    ///
+    /// Either limit leaves the program at an instruction boundary, so it can be
+    /// resumed after raising the limit.
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Number of instructions executed so far.
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    fn check_limits(&self) -> Result<(), IntcodeError> {
+        let address = self.instruction_ptr;
+        if let Some(budget) = self.instruction_budget {
+            if self.executed >= budget {
+                return Err(IntcodeError::InstructionBudgetExceeded { address, budget });
+            }
+        }
+        if let Some(deadline) = self.deadline {
+            if self.executed.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline {
+                return Err(IntcodeError::DeadlineExceeded { address });
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues a value for the next input instruction; queued values are consumed
+    /// before the attached `IO` is consulted.
+    pub fn push_input(&mut self, value: Word) {
+        self.inputs.push_back(value);
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn instruction_ptr(&self) -> usize {
+        self.instruction_ptr
+    }
+
+    pub fn relative_base(&self) -> Word {
+        self.relative_base
+    }
+
+    /// Starts recording every executed instruction into a `Trace`.
+    pub fn enable_trace(&mut self) {
+        self.trace = Some(Trace::new());
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    /// Starts keeping an undo log so execution can be stepped backwards.
+    pub fn enable_history(&mut self) {
+        self.history = Some(History::new());
+    }
+
+    pub fn history(&self) -> Option<&History> {
+        self.history.as_ref()
+    }
+
+    /// Undoes the most recently executed instruction, restoring the memory cell
+    /// it wrote, the pointer, the relative base and any input it consumed. The
+    /// instruction no longer counts towards `executed` or the instruction budget.
+    ///
+    /// Like outputs, the trace, profile, coverage and self-modification records
+    /// are not rolled back: running forward again records the instruction twice.
+    ///
+    /// Returns `None` when history is disabled or empty.
+    pub fn step_back(&mut self) -> Option<UndoEntry> {
+        let entry = self.history.as_mut()?.pop()?;
+        if let Some(write) = entry.write {
+            self.instructions[write.address] = write.old;
+            self.decoded.invalidate(write.address);
+        }
+        if let Some(input) = entry.input {
+            self.inputs.push_front(input);
+        }
+        self.instruction_ptr = entry.address;
+        self.relative_base = entry.relative_base;
+        self.halted = false;
+        self.executed -= 1;
+        debug!("{:?} => STEP BACK TO {}", thread::current().name(), entry.address);
+        Some(entry)
+    }
+
+    /// Starts counting executed instructions per op code and address.
+    pub fn enable_profiler(&mut self) {
+        self.profile = Some(Profile::new());
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Starts recording executed instructions and branch outcomes.
+    pub fn enable_coverage(&mut self) {
+        self.coverage = Some(Coverage::new());
+    }
+
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    /// Starts tracking which cells execute as code and checking every write
+    /// against them, either warning or failing depending on `mode`.
+    pub fn enable_self_modification_check(&mut self, mode: self_modification::Mode) {
+        self.self_modifications = Some(SelfModifications::new(mode));
+    }
+
+    pub fn self_modifications(&self) -> Option<&SelfModifications> {
+        self.self_modifications.as_ref()
+    }
+
+    /// Watches reads and/or writes of the addresses in `range` and returns an id
+    /// for `unwatch`. Instruction fetches do not count as reads.
+    pub fn watch(&mut self, range: Range<usize>, access: Access, action: WatchAction) -> usize {
+        self.watchpoints.add(Watchpoint {
+            range,
+            access,
+            action,
+        })
+    }
+
+    pub fn unwatch(&mut self, watchpoint: usize) {
+        self.watchpoints.remove(watchpoint);
+    }
+
+    /// Takes the oldest pausing watchpoint hit not yet reported.
+    ///
+    /// Hits pile up here when the program is driven by `step` alone; callers
+    /// doing so should drain them.
+    pub fn next_watch_hit(&mut self) -> Option<WatchHit> {
+        self.watch_hits.pop_front()
+    }
+
+    /// Drops pausing watchpoint hits not yet reported.
+    pub fn clear_watch_hits(&mut self) {
+        self.watch_hits.clear();
+    }
+
+    /// The oldest unreported watchpoint hit, else the event deferred behind it.
+    pub(crate) fn pending_event(&mut self) -> Option<Event> {
+        match self.next_watch_hit() {
+            Some(hit) => Some(Event::Watchpoint(hit)),
+            None => self.deferred_event.take(),
+        }
+    }
+
+    /// Queues `event` behind the watchpoint hits of the instruction raising it,
+    /// to be reported by `pending_event`.
+    pub(crate) fn defer_event(&mut self, event: Event) {
+        self.deferred_event = Some(event);
+    }
+
+    fn watch_access(&mut self, address: usize, access: Access, old: Word, new: Word) {
+        if self.watchpoints.is_empty() {
+            return;
+        }
+        let pauses = self
+            .watchpoints
+            .check(address, self.effects.address, access, old, new);
+        self.watch_hits.extend(pauses);
+    }
+
+    /// Adds an op code with `param_count` parameters, executed by `handler`.
+    ///
+    /// The handler reads its parameters in order through `parameter` and
+    /// `location`; unless it jumps, execution continues after the last
+    /// parameter. Built-in op codes cannot be replaced; registering a custom
+    /// one again replaces its handler and parameter count.
+    pub fn register_op_code(
+        &mut self,
+        code: Word,
+        param_count: usize,
+        handler: Handler,
+    ) -> Result<(), IntcodeError> {
+        if !(1..100).contains(&code) || OpCodes::op_code(code).is_some() || param_count > 3 {
+            return Err(IntcodeError::InvalidCustomOpCode { code, param_count });
+        }
+        self.extensions.register(code, param_count, handler);
+        self.decoded = DecodeCache::default();
+        Ok(())
+    }
+
+    /// Reads the next parameter of a custom instruction, resolving its mode.
+    pub fn parameter(&mut self, mode: Word) -> Result<Word, IntcodeError> {
+        OpCodes::get_parameter(mode, self)
+    }
+
+    /// Reads the next parameter of a custom instruction as a write target.
+    pub fn location(&mut self, mode: Word) -> Result<usize, IntcodeError> {
+        OpCodes::get_location(mode, self)
+    }
+
+    /// Writes `data` to a location obtained from `location`.
+    pub fn store(&mut self, location: usize, data: Word) -> Result<(), IntcodeError> {
+        self.set_position(location, data)
+    }
+
+    /// Moves the pointer to `target` once the custom instruction finishes.
+    pub fn jump_to(&mut self, target: Word) -> Result<(), IntcodeError> {
+        let target = self.address(target)?;
+        self.jump(target);
+        Ok(())
+    }
+
+    /// Decodes the word `c` found at `address`, including registered op codes.
+    fn decode(&self, address: usize, c: Word) -> Result<Instruction, IntcodeError> {
+        match self.extensions.param_count(c % 100) {
+            Some(param_count) => Ok(Instruction::custom(address, c, param_count)),
+            None => Instruction::decode(address, c),
+        }
+    }
+
+    /// Number of cells the word at `address` occupies when decoded as an
+    /// instruction, counting registered op codes; 1 if it does not decode.
+    fn instruction_len(&self, address: usize) -> usize {
+        self.instructions
+            .get(address)
+            .and_then(|&word| match self.extensions.param_count(word % 100) {
+                Some(param_count) => Some(param_count),
+                None => OpCodes::op_code(word % 100).map(|code| code.param_count()),
+            })
+            .map_or(1, |param_count| 1 + param_count)
+    }
+
+    /// Executes the instruction at the pointer and reports its effects.
+    pub fn step(&mut self) -> Result<Step, IntcodeError> {
+        self.effects = Effects::default();
+        self.effects.address = self.instruction_ptr;
+        if self.halted {
+            let c = self.at_position(self.instruction_ptr)?;
+            let instruction = self.decode(self.instruction_ptr, c)?;
+            return Ok(self.finish_step(instruction, Some(Event::Halted)));
+        }
+        self.check_limits()?;
+        let relative_base = self.relative_base;
+        let instruction = Instruction::new(self)?;
+        if let Some(self_modifications) = self.self_modifications.as_mut() {
+            self_modifications.mark(&instruction);
+        }
+        let event = match instruction.process(self)? {
+            Action::Continue => None,
+            Action::Output(data) => Some(Event::Output(data)),
+            Action::NeedInput => Some(Event::NeedInput),
+            Action::Halt => {
+                self.halted = true;
+                Some(Event::Halted)
+            }
+        };
+        let step = self.finish_step(instruction, event);
+        if step.event != Some(Event::NeedInput) {
+            self.executed += 1;
+            self.observe(&step, relative_base);
+        }
+        Ok(step)
+    }
+
+    /// Feeds an executed instruction to the enabled recorders.
+    fn observe(&mut self, step: &Step, relative_base: Word) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.record(step);
+        }
+        if let Some(history) = self.history.as_mut() {
+            history.record(step, relative_base);
+        }
+        if let Some(profile) = self.profile.as_mut() {
+            profile.record(step);
+        }
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.record(step);
+        }
+    }
+
+    fn finish_step(&self, instruction: Instruction, event: Option<Event>) -> Step {
+        Step {
+            instruction,
+            operands: self.effects.operands,
+            write: self.effects.write,
+            input: self.effects.input,
+            jump: self.effects.jump,
+            relative_base: self.effects.relative_base,
+            event,
+        }
+    }
+
+    /// Runs until the program produces an output, blocks on input or halts.
+    ///
+    /// Inputs come from `push_input`, falling back to the attached `IO` if any.
+    /// Outputs are returned to the caller rather than written to the `IO`.
+    pub fn run_until_event(&mut self) -> Result<Event, IntcodeError> {
+        loop {
+            if let Some(event) = self.pending_event() {
+                return Ok(event);
+            }
+            if let Some(event) = self.step()?.event {
+                self.defer_event(event);
+            }
+        }
+    }
+
+    fn next(&mut self) -> Result<Word, IntcodeError> {
+        self.instruction_ptr += 1;
+        let ins = self.at_position(self.instruction_ptr - 1)?;
         debug!("{:?} =>  [ {} : {} ] ", thread::current().name(), self.instruction_ptr - 1, ins);
-        ins
+        Ok(ins)
     }
 
     fn set_pointer(&mut self, ptr: usize) {
         self.instruction_ptr = ptr;
     }
 
-    fn at_position(&self, position: usize) -> i32 {
-        self.instructions[position]
+    fn jump(&mut self, ptr: usize) {
+        self.effects.jump = Some(ptr);
+        self.set_pointer(ptr);
+    }
+
+    fn adjust_relative_base(&mut self, offset: Word) -> Result<(), IntcodeError> {
+        self.relative_base = self
+            .relative_base
+            .checked_add(offset)
+            .ok_or_else(|| self.overflow())?;
+        self.effects.relative_base = Some(self.relative_base);
+        Ok(())
+    }
+
+    /// Error for arithmetic overflow in the instruction being executed.
+    fn overflow(&self) -> IntcodeError {
+        IntcodeError::Overflow {
+            address: self.effects.address,
+        }
+    }
+
+    /// Converts a relative-mode `parameter` into a memory address.
+    fn relative_address(&self, parameter: Word) -> Result<usize, IntcodeError> {
+        let target = self
+            .relative_base
+            .checked_add(parameter)
+            .ok_or_else(|| self.overflow())?;
+        self.address(target)
     }
 
-    fn set_position(&mut self, position: usize, data: i32) {
+    /// Converts `target`, referenced by the parameter just read, into a memory address.
+    fn address(&self, target: Word) -> Result<usize, IntcodeError> {
+        if target < 0 {
+            return Err(IntcodeError::NegativeAddress {
+                address: self.instruction_ptr - 1,
+                target,
+            });
+        }
+        Ok(target as usize)
+    }
+
+    fn check_address(&self, position: usize) -> Result<(), IntcodeError> {
+        if position >= self.memory_limit {
+            return Err(IntcodeError::AddressOutOfRange {
+                target: position,
+                limit: self.memory_limit,
+            });
+        }
+        Ok(())
+    }
+
+    fn at_position(&self, position: usize) -> Result<Word, IntcodeError> {
+        self.check_address(position)?;
+        Ok(self.instructions.get(position).copied().unwrap_or(0))
+    }
+
+    fn set_position(&mut self, position: usize, data: Word) -> Result<(), IntcodeError> {
+        self.check_address(position)?;
+        if position >= self.instructions.len() {
+            debug!("{:?} => GROW MEMORY TO {}", thread::current().name(), position + 1);
+            self.instructions.resize(position + 1, 0);
+        }
+        let old = self.instructions[position];
+        if self.self_modifications.is_some() {
+            let (address, next) = (self.effects.address, self.instruction_ptr);
+            let next_len = self.instruction_len(next);
+            let self_modifications = self.self_modifications.as_mut().unwrap();
+            if let Err(e) = self_modifications.check(address, next, next_len, position, old, data) {
+                self.set_pointer(address);
+                return Err(e);
+            }
+        }
+        self.effects.write = Some(Write {
+            address: position,
+            old,
+            new: data,
+        });
         self.instructions[position] = data;
+        self.decoded.invalidate(position);
+        self.watch_access(position, Access::Write, old, data);
+        Ok(())
     }
 
-    fn set_position_from_input(&mut self, position: usize) {
+    fn set_position_from_input(&mut self, position: usize) -> Result<bool, IntcodeError> {
         debug!("{:?} => SET INPUT AT {}, ", thread::current().name(), position);
-        self.instructions[position] = self.from_input();
+        match self.read_input() {
+            Some(data) => {
+                self.set_position(position, data)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
     }
 
-    fn from_input(&mut self) -> i32 {
-        let x = self.io.as_mut().unwrap().read();
+    fn read_input(&mut self) -> Option<Word> {
+        let x = match self.inputs.pop_front() {
+            Some(x) => Some(x),
+            None => self.io.as_mut().and_then(|io| io.read()),
+        };
         debug!("{:?} => READ INPUT : {:?}", thread::current().name(), x);
+        self.effects.input = x;
         x
     }
 
-    fn to_output(&mut self, data: i32) {
+    fn write_output(&mut self, data: Word) -> Result<(), IntcodeError> {
         debug!("{:?} => WRITE OUTPUT : {}", thread::current().name(), data);
-        self.io.as_mut().unwrap().write(data);
+        self.io.as_mut().ok_or(IntcodeError::MissingIO)?.write(data);
+        Ok(())
     }
 }
 
-struct Instruction {
-    code: OpCodes,
-    param_modes: Vec<i32>,
+/// A decoded instruction: its op code and the modes of its parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub address: usize,
+    pub code: OpCodes,
+    modes: [Word; 3],
 }
 
 impl Instruction {
-    fn get_param_modes(n: usize, mut c: i32) -> Vec<i32> {
-        let mut p = Vec::new();
-        for _ in 0..n {
-            p.push(c % 10);
-            c = c / 10;
+    fn get_param_modes(n: usize, mut c: Word) -> [Word; 3] {
+        let mut p = [0; 3];
+        for mode in p.iter_mut().take(n) {
+            *mode = c % 10;
+            c /= 10;
         }
-        debug!("{:?} =>  < {:?} > ", thread::current().name(), p);
+        debug!("{:?} =>  < {:?} > ", thread::current().name(), &p[..n]);
         p
     }
 
-    fn new(c: i32) -> Self {
-        let t = c % 100;
-        let code = OpCodes::op_code(t);
-        let param_modes = Instruction::get_param_modes(code.param_count(), c / 100);
-        Instruction { code, param_modes }
+    fn decode(address: usize, c: Word) -> Result<Self, IntcodeError> {
+        let code =
+            OpCodes::op_code(c % 100).ok_or(IntcodeError::InvalidOpCode { address, code: c })?;
+        let modes = Instruction::get_param_modes(code.param_count(), c / 100);
+        Ok(Instruction {
+            address,
+            code,
+            modes,
+        })
     }
 
-    fn process(&self, program: &mut Program) -> Action {
-        if self.code.process(program, &self.param_modes) {
-            Action::CONTINUE
-        } else {
-            Action::HALT
+    fn custom(address: usize, c: Word, param_count: usize) -> Self {
+        Instruction {
+            address,
+            code: OpCodes::Custom {
+                code: c % 100,
+                param_count,
+            },
+            modes: Instruction::get_param_modes(param_count, c / 100),
+        }
+    }
+
+    /// Fetches the instruction at the pointer, decoding it only if the decode
+    /// cache has no entry for the word found there.
+    fn new(program: &mut Program) -> Result<Self, IntcodeError> {
+        let address = program.instruction_ptr;
+        let c = program.next()?;
+        if let Some(instruction) = program.decoded.get(address, c) {
+            return Ok(instruction);
         }
+        let instruction = program.decode(address, c)?;
+        program.decoded.insert(instruction, c);
+        Ok(instruction)
+    }
+
+    pub fn param_modes(&self) -> &[Word] {
+        &self.modes[..self.code.param_count()]
+    }
+
+    fn process(&self, program: &mut Program) -> Result<Action, IntcodeError> {
+        let action = self.code.process(program, self.param_modes())?;
+        match action {
+            Action::NeedInput => {
+                debug!("{:?} => WAITING FOR INPUT", thread::current().name());
+                program.set_pointer(self.address);
+            }
+            Action::Halt => program.set_pointer(self.address),
+            _ => (),
+        }
+        Ok(action)
     }
 }
 
-enum OpCodes {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpCodes {
     Add,
     Multiply,
     Input,
@@ -98,22 +743,26 @@ enum OpCodes {
     JumpIfFalse,
     LessThan,
     Equals,
+    AdjustRelativeBase,
     Halt,
+    /// An op code added with `Program::register_op_code`.
+    Custom { code: Word, param_count: usize },
 }
 
 impl OpCodes {
-    fn op_code(t: i32) -> Self {
+    fn op_code(t: Word) -> Option<Self> {
         match t {
-            1 => OpCodes::Add,
-            2 => OpCodes::Multiply,
-            3 => OpCodes::Input,
-            4 => OpCodes::Output,
-            5 => OpCodes::JumpIfTrue,
-            6 => OpCodes::JumpIfFalse,
-            7 => OpCodes::LessThan,
-            8 => OpCodes::Equals,
-            99 => OpCodes::Halt,
-            _ => panic!("INVALID OP CODE"),
+            1 => Some(OpCodes::Add),
+            2 => Some(OpCodes::Multiply),
+            3 => Some(OpCodes::Input),
+            4 => Some(OpCodes::Output),
+            5 => Some(OpCodes::JumpIfTrue),
+            6 => Some(OpCodes::JumpIfFalse),
+            7 => Some(OpCodes::LessThan),
+            8 => Some(OpCodes::Equals),
+            9 => Some(OpCodes::AdjustRelativeBase),
+            99 => Some(OpCodes::Halt),
+            _ => None,
         }
     }
 
@@ -127,11 +776,90 @@ impl OpCodes {
             OpCodes::JumpIfFalse => 2,
             OpCodes::LessThan => 3,
             OpCodes::Equals => 3,
+            OpCodes::AdjustRelativeBase => 1,
             OpCodes::Halt => 0,
+            OpCodes::Custom { param_count, .. } => *param_count,
+        }
+    }
+
+    /// Whether the last parameter is the address the instruction writes to.
+    fn writes(&self) -> bool {
+        matches!(
+            self,
+            OpCodes::Add | OpCodes::Multiply | OpCodes::Input | OpCodes::LessThan | OpCodes::Equals
+        )
+    }
+
+    fn value(&self) -> Word {
+        match self {
+            OpCodes::Add => 1,
+            OpCodes::Multiply => 2,
+            OpCodes::Input => 3,
+            OpCodes::Output => 4,
+            OpCodes::JumpIfTrue => 5,
+            OpCodes::JumpIfFalse => 6,
+            OpCodes::LessThan => 7,
+            OpCodes::Equals => 8,
+            OpCodes::AdjustRelativeBase => 9,
+            OpCodes::Halt => 99,
+            OpCodes::Custom { code, .. } => *code,
+        }
+    }
+
+    fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        match mnemonic {
+            "ADD" => Some(OpCodes::Add),
+            "MUL" => Some(OpCodes::Multiply),
+            "IN" => Some(OpCodes::Input),
+            "OUT" => Some(OpCodes::Output),
+            "JT" => Some(OpCodes::JumpIfTrue),
+            "JF" => Some(OpCodes::JumpIfFalse),
+            "LT" => Some(OpCodes::LessThan),
+            "EQ" => Some(OpCodes::Equals),
+            "ARB" => Some(OpCodes::AdjustRelativeBase),
+            "HLT" => Some(OpCodes::Halt),
+            _ => None,
+        }
+    }
+
+    /// Parses a mnemonic as written by `name`, giving a custom op code
+    /// `param_count` parameters.
+    fn from_name(name: &str, param_count: usize) -> Option<Self> {
+        if let Some(code) = name.strip_prefix("EXT") {
+            let code = code.parse::<Word>().ok()?;
+            if !(1..100).contains(&code) || OpCodes::op_code(code).is_some() {
+                return None;
+            }
+            return Some(OpCodes::Custom { code, param_count });
+        }
+        OpCodes::from_mnemonic(name)
+    }
+
+    /// The mnemonic, with the op code appended for custom ones, e.g. `EXT50`.
+    pub fn name(&self) -> String {
+        match self {
+            OpCodes::Custom { code, .. } => format!("EXT{}", code),
+            _ => self.mnemonic().to_string(),
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            OpCodes::Add => "ADD",
+            OpCodes::Multiply => "MUL",
+            OpCodes::Input => "IN",
+            OpCodes::Output => "OUT",
+            OpCodes::JumpIfTrue => "JT",
+            OpCodes::JumpIfFalse => "JF",
+            OpCodes::LessThan => "LT",
+            OpCodes::Equals => "EQ",
+            OpCodes::AdjustRelativeBase => "ARB",
+            OpCodes::Halt => "HLT",
+            OpCodes::Custom { .. } => "EXT",
         }
     }
 
-    fn process(&self, program: &mut Program, param_modes: &Vec<i32>) -> bool {
+    fn process(&self, program: &mut Program, param_modes: &[Word]) -> Result<Action, IntcodeError> {
         match self {
             OpCodes::Add => OpCodes::process_add(program, param_modes),
             OpCodes::Multiply => OpCodes::process_multiply(program, param_modes),
@@ -141,115 +869,355 @@ impl OpCodes {
             OpCodes::JumpIfFalse => OpCodes::process_jump_if_false(program, param_modes),
             OpCodes::LessThan => OpCodes::process_less_than(program, param_modes),
             OpCodes::Equals => OpCodes::process_equals(program, param_modes),
+            OpCodes::AdjustRelativeBase => {
+                OpCodes::process_adjust_relative_base(program, param_modes)
+            }
             OpCodes::Halt => {
                 debug!("{:?} => HALTING", thread::current().name());
-                false
+                Ok(Action::Halt)
             },
+            OpCodes::Custom { code, .. } => OpCodes::process_custom(program, *code, param_modes),
         }
     }
 
-    fn process_add(program: &mut Program, param_modes: &Vec<i32>) -> bool {
-        let op1 = OpCodes::get_parameter(param_modes[0], program);
-        let op2 = OpCodes::get_parameter(param_modes[1], program);
-        let location = program.next() as usize;
+    fn process_add(program: &mut Program, param_modes: &[Word]) -> Result<Action, IntcodeError> {
+        let op1 = OpCodes::get_parameter(param_modes[0], program)?;
+        let op2 = OpCodes::get_parameter(param_modes[1], program)?;
+        let location = OpCodes::get_location(param_modes[2], program)?;
         debug!("{:?} => ADD {} and {}", thread::current().name(), op1, op2);
-        program.set_position(location, op1 + op2);
-        true
+        let sum = op1.checked_add(op2).ok_or_else(|| program.overflow())?;
+        program.set_position(location, sum)?;
+        Ok(Action::Continue)
     }
 
-    fn process_multiply(program: &mut Program, param_modes: &Vec<i32>) -> bool {
-        let op1 = OpCodes::get_parameter(param_modes[0], program);
-        let op2 = OpCodes::get_parameter(param_modes[1], program);
-        let location = program.next() as usize;
+    fn process_multiply(
+        program: &mut Program,
+        param_modes: &[Word],
+    ) -> Result<Action, IntcodeError> {
+        let op1 = OpCodes::get_parameter(param_modes[0], program)?;
+        let op2 = OpCodes::get_parameter(param_modes[1], program)?;
+        let location = OpCodes::get_location(param_modes[2], program)?;
         debug!("{:?} => MULTIPLY {} and {}", thread::current().name(), op1, op2);
-        program.set_position(location, op1 * op2);
-        true
+        let product = op1.checked_mul(op2).ok_or_else(|| program.overflow())?;
+        program.set_position(location, product)?;
+        Ok(Action::Continue)
     }
 
-    fn process_input(program: &mut Program, _param_modes: &Vec<i32>) -> bool {
-        let location = program.next() as usize;
-        program.set_position_from_input(location);
-        true
+    fn process_input(program: &mut Program, param_modes: &[Word]) -> Result<Action, IntcodeError> {
+        let location = OpCodes::get_location(param_modes[0], program)?;
+        if program.set_position_from_input(location)? {
+            Ok(Action::Continue)
+        } else {
+            Ok(Action::NeedInput)
+        }
     }
 
-    fn process_output(program: &mut Program, param_modes: &Vec<i32>) -> bool {
-        let data = OpCodes::get_parameter(param_modes[0], program);
+    fn process_output(program: &mut Program, param_modes: &[Word]) -> Result<Action, IntcodeError> {
+        let data = OpCodes::get_parameter(param_modes[0], program)?;
         debug!("{:?} => OUTPUT : {}", thread::current().name(), data);
-        program.to_output(data);
-        true
+        Ok(Action::Output(data))
     }
 
-    fn process_jump_if_true(program: &mut Program, param_modes: &Vec<i32>) -> bool {
-        let op1 = OpCodes::get_parameter(param_modes[0], program);
-        let op2 = OpCodes::get_parameter(param_modes[1], program);
+    fn process_jump_if_true(
+        program: &mut Program,
+        param_modes: &[Word],
+    ) -> Result<Action, IntcodeError> {
+        let op1 = OpCodes::get_parameter(param_modes[0], program)?;
+        let op2 = OpCodes::get_parameter(param_modes[1], program)?;
         if op1 != 0 {
             debug!("{:?} => Jump to {}", thread::current().name(), op2);
-            program.set_pointer(op2 as usize);
+            let target = program.address(op2)?;
+            program.jump(target);
         } else {
             debug!("{:?} => No Jump", thread::current().name());
         }
-        true
+        Ok(Action::Continue)
     }
 
-    fn process_jump_if_false(program: &mut Program, param_modes: &Vec<i32>) -> bool {
-        let op1 = OpCodes::get_parameter(param_modes[0], program);
-        let op2 = OpCodes::get_parameter(param_modes[1], program);
+    fn process_jump_if_false(
+        program: &mut Program,
+        param_modes: &[Word],
+    ) -> Result<Action, IntcodeError> {
+        let op1 = OpCodes::get_parameter(param_modes[0], program)?;
+        let op2 = OpCodes::get_parameter(param_modes[1], program)?;
         if op1 == 0 {
             debug!("{:?} => Jump to {}", thread::current().name(), op2);
-            program.set_pointer(op2 as usize);
+            let target = program.address(op2)?;
+            program.jump(target);
         } else {
             debug!("{:?} => No Jump", thread::current().name());
         }
-        true
+        Ok(Action::Continue)
     }
 
-    fn process_less_than(program: &mut Program, param_modes: &Vec<i32>) -> bool {
-        let op1 = OpCodes::get_parameter(param_modes[0], program);
-        let op2 = OpCodes::get_parameter(param_modes[1], program);
-        let location = program.next() as usize;
+    fn process_less_than(
+        program: &mut Program,
+        param_modes: &[Word],
+    ) -> Result<Action, IntcodeError> {
+        let op1 = OpCodes::get_parameter(param_modes[0], program)?;
+        let op2 = OpCodes::get_parameter(param_modes[1], program)?;
+        let location = OpCodes::get_location(param_modes[2], program)?;
         debug!("{:?} =>  {} LT? {}", thread::current().name(), op1, op2);
         if op1 < op2 {
-            program.set_position(location, 1);
+            program.set_position(location, 1)?;
         } else {
-            program.set_position(location, 0);
+            program.set_position(location, 0)?;
         }
-        true
+        Ok(Action::Continue)
     }
 
-    fn process_equals(program: &mut Program, param_modes: &Vec<i32>) -> bool {
-        let op1 = OpCodes::get_parameter(param_modes[0], program);
-        let op2 = OpCodes::get_parameter(param_modes[1], program);
-        let location = program.next() as usize;
+    fn process_equals(program: &mut Program, param_modes: &[Word]) -> Result<Action, IntcodeError> {
+        let op1 = OpCodes::get_parameter(param_modes[0], program)?;
+        let op2 = OpCodes::get_parameter(param_modes[1], program)?;
+        let location = OpCodes::get_location(param_modes[2], program)?;
         debug!("{:?} =>  {} EQ? {}", thread::current().name(), op1, op2);
         if op1 == op2 {
-            program.set_position(location, 1);
+            program.set_position(location, 1)?;
         } else {
-            program.set_position(location, 0);
+            program.set_position(location, 0)?;
         }
-        true
+        Ok(Action::Continue)
+    }
+
+    fn process_adjust_relative_base(
+        program: &mut Program,
+        param_modes: &[Word],
+    ) -> Result<Action, IntcodeError> {
+        let offset = OpCodes::get_parameter(param_modes[0], program)?;
+        debug!("{:?} => ADJUST RELATIVE BASE BY {}", thread::current().name(), offset);
+        program.adjust_relative_base(offset)?;
+        Ok(Action::Continue)
     }
 
-    fn get_parameter(parameter_mode: i32, program: &mut Program) -> i32 {
-        let parameter = program.next();
-        match parameter_mode {
-            0 => program.at_position(parameter as usize),
-            1 => parameter,
-            _ => panic!("Invalid parameter mode!"),
+    fn process_custom(
+        program: &mut Program,
+        code: Word,
+        param_modes: &[Word],
+    ) -> Result<Action, IntcodeError> {
+        let address = program.effects.address;
+        let mut handler = program
+            .extensions
+            .take(code)
+            .ok_or(IntcodeError::InvalidOpCode { address, code })?;
+        debug!("{:?} => CUSTOM OP CODE {}", thread::current().name(), code);
+        let action = handler(program, param_modes);
+        program.extensions.put_back(code, handler);
+        if program.effects.jump.is_none() {
+            program.set_pointer(address + 1 + param_modes.len());
         }
+        action
+    }
+
+    fn get_parameter(parameter_mode: Word, program: &mut Program) -> Result<Word, IntcodeError> {
+        let parameter = program.next()?;
+        let location = match parameter_mode {
+            0 => program.address(parameter)?,
+            1 => {
+                program.effects.push_operand(parameter);
+                return Ok(parameter);
+            }
+            2 => program.relative_address(parameter)?,
+            _ => {
+                return Err(IntcodeError::InvalidParameterMode {
+                    address: program.instruction_ptr - 1,
+                    mode: parameter_mode,
+                })
+            }
+        };
+        let value = program.at_position(location)?;
+        program.watch_access(location, Access::Read, value, value);
+        program.effects.push_operand(value);
+        Ok(value)
+    }
+
+    fn get_location(parameter_mode: Word, program: &mut Program) -> Result<usize, IntcodeError> {
+        let parameter = program.next()?;
+        let location = match parameter_mode {
+            0 => program.address(parameter),
+            1 => Err(IntcodeError::ImmediateWriteTarget {
+                address: program.instruction_ptr - 1,
+            }),
+            2 => program.relative_address(parameter),
+            _ => Err(IntcodeError::InvalidParameterMode {
+                address: program.instruction_ptr - 1,
+                mode: parameter_mode,
+            }),
+        }?;
+        program.effects.push_operand(location as Word);
+        Ok(location)
     }
 }
 
-enum Action {
-    HALT,
-    CONTINUE,
+/// What an instruction asks the interpreter to do once it has executed.
+pub enum Action {
+    Halt,
+    Continue,
+    Output(Word),
+    /// No input was available; the instruction is retried once there is.
+    NeedInput,
 }
 
-pub fn process(program: &mut Program) {
+/// Runs the program to completion, feeding it from and writing to its `IO`.
+pub fn process(program: &mut Program) -> Result<(), IntcodeError> {
     loop {
-        let instruction = Instruction::new(program.next());
-        match instruction.process(program) {
-            Action::HALT => break,
-            _ => (),
+        match program.run_until_event()? {
+            Event::Output(data) => program.write_output(data)?,
+            Event::Watchpoint(_) => (),
+            Event::NeedInput if program.io.is_none() => return Err(IntcodeError::MissingIO),
+            Event::NeedInput => return Err(IntcodeError::InputExhausted),
+            Event::Halted => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(image: Vec<Word>) -> Result<(), IntcodeError> {
+        process(&mut Program::new(image, None))
+    }
+
+    /// Runs `image` to completion on `inputs` and returns its outputs.
+    fn outputs(image: Vec<Word>, inputs: &[Word]) -> Vec<Word> {
+        let mut program = Program::new(image, None);
+        for &input in inputs {
+            program.push_input(input);
+        }
+        let mut outputs = Vec::new();
+        loop {
+            match program.run_until_event().unwrap() {
+                Event::Output(value) => outputs.push(value),
+                Event::Halted => return outputs,
+                event => panic!("unexpected {:?}", event),
+            }
         }
     }
+
+    #[test]
+    fn quine_outputs_itself() {
+        let quine = vec![109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+        assert_eq!(outputs(quine.clone(), &[]), quine);
+    }
+
+    #[test]
+    fn relative_mode_write_targets() {
+        // ARB #20; IN -> [rb+2]; ADD #3, #4 -> [rb+3]; OUT [rb+2]; OUT [rb+3]; HLT
+        let image = vec![109, 20, 203, 2, 21101, 3, 4, 3, 204, 2, 204, 3, 99];
+        assert_eq!(outputs(image.clone(), &[42]), vec![42, 7]);
+
+        let mut program = Program::new(image, None);
+        program.push_input(42);
+        program.run_until_event().unwrap();
+        assert_eq!(program.instructions[22..24], [42, 7]);
+    }
+
+    #[test]
+    fn memory_beyond_the_image_reads_as_zero() {
+        let mut program = Program::new(vec![4, 100, 99], None);
+        assert_eq!(program.run_until_event(), Ok(Event::Output(0)));
+        assert_eq!(program.instructions.len(), 3);
+    }
+
+    #[test]
+    fn writes_beyond_the_image_grow_memory_with_zeros() {
+        let mut program = Program::new(vec![1101, 1, 2, 10, 99], None);
+        assert_eq!(process(&mut program), Ok(()));
+        assert_eq!(program.instructions, vec![1101, 1, 2, 10, 99, 0, 0, 0, 0, 0, 3]);
+    }
+
+    #[test]
+    fn memory_limit_rejects_reads_and_writes_past_it() {
+        for image in [vec![1101, 1, 2, 10, 99], vec![4, 10, 99]] {
+            let mut program = Program::new(image, None);
+            program.set_memory_limit(10);
+            assert_eq!(
+                process(&mut program),
+                Err(IntcodeError::AddressOutOfRange {
+                    target: 10,
+                    limit: 10
+                })
+            );
+            assert!(program.instructions.len() < 10);
+        }
+    }
+
+    #[test]
+    fn instruction_budget_stops_at_an_instruction_boundary() {
+        let counter = vec![1001, 12, 1, 12, 1008, 12, 3, 13, 1006, 13, 0, 99, 0, 0];
+        let mut program = Program::new(counter, None);
+        program.set_instruction_budget(4);
+        assert_eq!(
+            process(&mut program),
+            Err(IntcodeError::InstructionBudgetExceeded {
+                address: 4,
+                budget: 4
+            })
+        );
+        assert_eq!(program.instruction_ptr(), 4);
+        assert_eq!(program.executed(), 4);
+
+        program.set_instruction_budget(100);
+        assert_eq!(process(&mut program), Ok(()));
+        assert_eq!(program.executed(), 10);
+        assert_eq!(program.instructions[12], 3);
+    }
+
+    #[test]
+    fn deadline_stops_an_infinite_loop() {
+        let mut program = Program::new(vec![1105, 1, 0], None);
+        program.set_deadline(Instant::now() + std::time::Duration::from_millis(20));
+        assert_eq!(
+            process(&mut program),
+            Err(IntcodeError::DeadlineExceeded { address: 0 })
+        );
+        assert!(program.executed() > 0);
+        assert!(program.executed().is_multiple_of(DEADLINE_CHECK_INTERVAL));
+    }
+
+    #[test]
+    fn step_back_returns_instructions_to_the_budget() {
+        let mut program = Program::new(vec![1101, 1, 2, 0, 1105, 1, 0], None);
+        program.enable_history();
+        program.set_instruction_budget(2);
+        program.step().unwrap();
+        program.step().unwrap();
+        program.step_back().unwrap();
+        program.step_back().unwrap();
+        assert_eq!(program.executed(), 0);
+        assert_eq!(program.instructions[0], 1101);
+        assert!(program.step().is_ok());
+        assert!(program.step().is_ok());
+        assert_eq!(
+            program.step().map(|s| s.event),
+            Err(IntcodeError::InstructionBudgetExceeded {
+                address: 0,
+                budget: 2
+            })
+        );
+    }
+
+    #[test]
+    fn multiply_overflow_is_an_error() {
+        let image = vec![1002, 7, 3, 7, 1105, 1, 0, Word::MAX / 2];
+        assert_eq!(run(image), Err(IntcodeError::Overflow { address: 0 }));
+    }
+
+    #[test]
+    fn add_overflow_is_an_error() {
+        let image = vec![1001, 5, 1, 0, 99, Word::MAX];
+        assert_eq!(run(image), Err(IntcodeError::Overflow { address: 0 }));
+    }
+
+    #[test]
+    fn relative_base_overflow_is_an_error() {
+        let image = vec![109, Word::MAX, 109, 1, 99];
+        assert_eq!(run(image), Err(IntcodeError::Overflow { address: 2 }));
+    }
+
+    #[test]
+    fn relative_address_overflow_is_an_error() {
+        let image = vec![109, Word::MAX, 204, 1, 99];
+        assert_eq!(run(image), Err(IntcodeError::Overflow { address: 2 }));
+    }
 }
@@ -0,0 +1,272 @@
+use super::scheduler::MachineError;
+use super::{OpCodes, Program, Word, IO};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+/// Address of the NAT, which receives packets sent to it and restarts an idle
+/// network by sending the last one to address 0.
+pub const NAT: Word = 255;
+
+/// Consecutive reads from an empty queue after which a machine counts as idle.
+pub const IDLE_READS: u32 = 2;
+
+/// Instructions a machine runs per turn unless it idles first.
+const QUANTUM: usize = 1000;
+
+/// Called with every packet sent on a `Network`.
+pub type Observer = Box<dyn FnMut(&Packet)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    pub source: Word,
+    pub destination: Word,
+    pub x: Word,
+    pub y: Word,
+}
+
+/// State shared between the network and the interfaces of its machines.
+struct Switch {
+    queues: Vec<VecDeque<Word>>,
+    empty_reads: Vec<u32>,
+    sent: VecDeque<Packet>,
+}
+
+/// Network interface of one machine: reads its address once at boot, then
+/// packet values from its queue, or -1 when the queue is empty. Every three
+/// outputs form a packet.
+struct Nic {
+    address: usize,
+    booted: bool,
+    pending: Vec<Word>,
+    switch: Rc<RefCell<Switch>>,
+}
+
+impl IO for Nic {
+    fn read(&mut self) -> Option<Word> {
+        if !self.booted {
+            self.booted = true;
+            return Some(self.address as Word);
+        }
+        let mut switch = self.switch.borrow_mut();
+        match switch.queues[self.address].pop_front() {
+            Some(value) => {
+                switch.empty_reads[self.address] = 0;
+                Some(value)
+            }
+            None => {
+                switch.empty_reads[self.address] += 1;
+                Some(-1)
+            }
+        }
+    }
+
+    fn write(&mut self, o: Word) {
+        self.pending.push(o);
+        if self.pending.len() == 3 {
+            let mut switch = self.switch.borrow_mut();
+            switch.empty_reads[self.address] = 0;
+            switch.sent.push_back(Packet {
+                source: self.address as Word,
+                destination: self.pending[0],
+                x: self.pending[1],
+                y: self.pending[2],
+            });
+            self.pending.clear();
+        }
+    }
+}
+
+/// Machines running the same image, exchanging `(address, X, Y)` packets on
+/// one thread, with a NAT at address `NAT`.
+///
+/// The network is idle once every machine still running has an empty queue and
+/// has read from it `IDLE_READS` times in a row without sending anything.
+pub struct Network {
+    machines: Vec<Program>,
+    switch: Rc<RefCell<Switch>>,
+    nat: Option<Packet>,
+    observers: Vec<Observer>,
+}
+
+impl Network {
+    pub fn new(image: &[Word], size: usize) -> Self {
+        let switch = Rc::new(RefCell::new(Switch {
+            queues: vec![VecDeque::new(); size],
+            empty_reads: vec![0; size],
+            sent: VecDeque::new(),
+        }));
+        let machines = (0..size)
+            .map(|address| {
+                let nic = Nic {
+                    address,
+                    booted: false,
+                    pending: Vec::new(),
+                    switch: switch.clone(),
+                };
+                Program::new(image.to_vec(), Some(Box::new(nic)))
+            })
+            .collect();
+        Network {
+            machines,
+            switch,
+            nat: None,
+            observers: Vec::new(),
+        }
+    }
+
+    /// Calls `observer` with every packet sent, including those sent by the NAT.
+    pub fn observe(&mut self, observer: Observer) {
+        self.observers.push(observer);
+    }
+
+    /// The last packet the NAT received.
+    pub fn nat(&self) -> Option<Packet> {
+        self.nat
+    }
+
+    pub fn machine(&self, address: usize) -> &Program {
+        &self.machines[address]
+    }
+
+    /// Runs the network until `stop` returns true for a packet being sent and
+    /// returns that packet. Returns `None` once every machine has halted, or if
+    /// the network goes idle while the NAT has nothing to restart it with or
+    /// machine 0 has halted.
+    pub fn run<F>(&mut self, mut stop: F) -> Result<Option<Packet>, MachineError>
+    where
+        F: FnMut(&Packet) -> bool,
+    {
+        loop {
+            for machine in 0..self.machines.len() {
+                self.turn(machine)?;
+                loop {
+                    let packet = match self.switch.borrow_mut().sent.pop_front() {
+                        Some(packet) => packet,
+                        None => break,
+                    };
+                    if self.route(packet, &mut stop) {
+                        return Ok(Some(packet));
+                    }
+                }
+            }
+            if self.machines.iter().all(Program::is_halted) {
+                return Ok(None);
+            }
+            if self.is_idle() {
+                let packet = match self.nat {
+                    Some(packet) if !self.machines[0].is_halted() => Packet {
+                        source: NAT,
+                        destination: 0,
+                        ..packet
+                    },
+                    _ => return Ok(None),
+                };
+                if self.route(packet, &mut stop) {
+                    return Ok(Some(packet));
+                }
+            }
+        }
+    }
+
+    /// Runs `machine` until it reads from its empty queue, halts or uses up
+    /// its quantum.
+    fn turn(&mut self, machine: usize) -> Result<(), MachineError> {
+        let program = &mut self.machines[machine];
+        for _ in 0..QUANTUM {
+            if program.is_halted() {
+                break;
+            }
+            let step = program.step().map_err(|error| MachineError { machine, error })?;
+            if let Some(data) = step.output() {
+                program.write_output(data).map_err(|error| MachineError { machine, error })?;
+            }
+            let idle = self.switch.borrow().empty_reads[machine] > 0;
+            if step.instruction.code == OpCodes::Input && idle {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delivers `packet` and reports whether `stop` matched it. Packets to
+    /// addresses with no machine are observed and dropped.
+    fn route<F>(&mut self, packet: Packet, stop: &mut F) -> bool
+    where
+        F: FnMut(&Packet) -> bool,
+    {
+        for observer in self.observers.iter_mut() {
+            observer(&packet);
+        }
+        let mut switch = self.switch.borrow_mut();
+        if packet.destination == NAT {
+            self.nat = Some(packet);
+        } else if packet.destination >= 0 && (packet.destination as usize) < self.machines.len() {
+            let destination = packet.destination as usize;
+            switch.queues[destination].push_back(packet.x);
+            switch.queues[destination].push_back(packet.y);
+            switch.empty_reads[destination] = 0;
+        }
+        stop(&packet)
+    }
+
+    fn is_idle(&self) -> bool {
+        let switch = self.switch.borrow();
+        switch.sent.is_empty()
+            && self
+                .machines
+                .iter()
+                .zip(switch.queues.iter().zip(&switch.empty_reads))
+                .all(|(program, (queue, &reads))| {
+                    program.is_halted() || (queue.is_empty() && reads >= IDLE_READS)
+                })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sends `(255, address, 7)` and then polls its queue forever, except that
+    /// machine 0 halts instead if `machine_0_halts` is set:
+    /// `IN -> [100]; OUT #255; OUT [100]; OUT #7; JF [100], #end; IN -> [101]; JT #1, #11; HLT`.
+    fn reporter(machine_0_halts: bool) -> Vec<Word> {
+        let end = if machine_0_halts { 16 } else { 11 };
+        vec![3, 100, 104, 255, 4, 100, 104, 7, 1006, 100, end, 3, 101, 1105, 1, 11, 99]
+    }
+
+    #[test]
+    fn the_nat_restarts_an_idle_network() {
+        let mut network = Network::new(&reporter(false), 2);
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let log = sent.clone();
+        network.observe(Box::new(move |p| log.borrow_mut().push(p.source)));
+        let packet = network.run(|p| p.source == NAT).unwrap();
+        assert_eq!(
+            packet,
+            Some(Packet {
+                source: NAT,
+                destination: 0,
+                x: 1,
+                y: 7
+            })
+        );
+        assert_eq!(*sent.borrow(), vec![0, 1, NAT]);
+    }
+
+    #[test]
+    fn stops_once_every_machine_has_halted() {
+        let mut network = Network::new(&[3, 100, 104, 255, 104, 1, 104, 2, 99], 2);
+        assert_eq!(network.run(|_| false), Ok(None));
+        assert_eq!(network.nat().map(|p| (p.x, p.y)), Some((1, 2)));
+    }
+
+    #[test]
+    fn stops_when_idle_with_machine_0_halted() {
+        let mut network = Network::new(&reporter(true), 2);
+        assert_eq!(network.run(|_| false), Ok(None));
+        assert!(network.machine(0).is_halted());
+        assert!(!network.machine(1).is_halted());
+        assert_eq!(network.nat().map(|p| p.source), Some(1));
+    }
+}
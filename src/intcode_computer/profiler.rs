@@ -0,0 +1,122 @@
+use super::{OpCodes, Step};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A backward jump, which closes a loop, with the number of times it was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HotLoop {
+    pub from: usize,
+    pub to: usize,
+    pub count: u64,
+}
+
+/// Execution counts gathered by `Program::enable_profiler`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub total: u64,
+    pub by_op_code: HashMap<OpCodes, u64>,
+    pub by_address: HashMap<usize, u64>,
+    pub backward_jumps: HashMap<(usize, usize), u64>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Profile::default()
+    }
+
+    pub fn record(&mut self, step: &Step) {
+        let address = step.instruction.address;
+        self.total += 1;
+        *self.by_op_code.entry(step.instruction.code).or_insert(0) += 1;
+        *self.by_address.entry(address).or_insert(0) += 1;
+        if let Some(target) = step.jump {
+            if target <= address {
+                *self.backward_jumps.entry((address, target)).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Backward jumps taken, most frequent first.
+    pub fn hot_loops(&self) -> Vec<HotLoop> {
+        let mut loops = self
+            .backward_jumps
+            .iter()
+            .map(|(&(from, to), &count)| HotLoop { from, to, count })
+            .collect::<Vec<HotLoop>>();
+        loops.sort_by(|a, b| b.count.cmp(&a.count).then(a.from.cmp(&b.from)));
+        loops
+    }
+
+    /// Instruction addresses with their execution counts, most frequent first.
+    pub fn hot_addresses(&self) -> Vec<(usize, u64)> {
+        let mut addresses = self
+            .by_address
+            .iter()
+            .map(|(&a, &c)| (a, c))
+            .collect::<Vec<(usize, u64)>>();
+        addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        addresses
+    }
+}
+
+/// Renders a summary: totals per op code, the ten hottest addresses and loops.
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "instructions executed: {}", self.total)?;
+        let mut op_codes = self.by_op_code.iter().collect::<Vec<(&OpCodes, &u64)>>();
+        op_codes.sort_by(|a, b| b.1.cmp(a.1).then(a.0.name().cmp(&b.0.name())));
+        writeln!(f, "by op code:")?;
+        for (code, count) in op_codes {
+            writeln!(f, "  {:<4} {}", code.name(), count)?;
+        }
+        writeln!(f, "hottest addresses:")?;
+        for (address, count) in self.hot_addresses().iter().take(10) {
+            writeln!(f, "  {:04} {}", address, count)?;
+        }
+        writeln!(f, "hot loops:")?;
+        for hot_loop in self.hot_loops().iter().take(10) {
+            writeln!(f, "  {:04} -> {:04} {}", hot_loop.from, hot_loop.to, hot_loop.count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{process, Program};
+    use super::*;
+
+    /// Counts [12] up to 3: `ADD [12], #1 -> [12]; EQ [12], #3 -> [13]; JF [13], #0; HLT`.
+    fn profiled_counter() -> Profile {
+        let image = vec![1001, 12, 1, 12, 1008, 12, 3, 13, 1006, 13, 0, 99, 0, 0];
+        let mut program = Program::new(image, None);
+        program.enable_profiler();
+        process(&mut program).unwrap();
+        program.profile().unwrap().clone()
+    }
+
+    #[test]
+    fn counts_per_op_code_and_address() {
+        let profile = profiled_counter();
+        assert_eq!(profile.total, 10);
+        assert_eq!(profile.by_op_code[&OpCodes::Add], 3);
+        assert_eq!(profile.by_op_code[&OpCodes::Equals], 3);
+        assert_eq!(profile.by_op_code[&OpCodes::JumpIfFalse], 3);
+        assert_eq!(profile.by_op_code[&OpCodes::Halt], 1);
+        assert_eq!(profile.hot_addresses(), vec![(0, 3), (4, 3), (8, 3), (11, 1)]);
+    }
+
+    #[test]
+    fn hot_loops_count_backward_jumps_taken() {
+        let profile = profiled_counter();
+        assert_eq!(
+            profile.hot_loops(),
+            vec![HotLoop {
+                from: 8,
+                to: 0,
+                count: 2
+            }]
+        );
+        assert!(profile.to_string().contains("  0008 -> 0000 2\n"));
+    }
+}
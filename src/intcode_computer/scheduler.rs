@@ -0,0 +1,243 @@
+use super::{Event, IntcodeError, Program, Word};
+use std::error::Error;
+use std::fmt;
+
+/// A value output by one of the scheduled machines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Output {
+    pub machine: usize,
+    pub value: Word,
+}
+
+/// How a scheduled run ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// Every machine halted.
+    Halted,
+    /// The listed machines are all blocked on input nobody will provide; the
+    /// others have halted.
+    Deadlock { blocked: Vec<usize> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub outcome: Outcome,
+    /// Every output in the order it was produced.
+    pub outputs: Vec<Output>,
+}
+
+impl Report {
+    /// The last value output by any machine, and which machine produced it.
+    pub fn final_output(&self) -> Option<Output> {
+        self.outputs.last().copied()
+    }
+
+    /// Values output by `machine`, in order.
+    pub fn outputs_of(&self, machine: usize) -> Vec<Word> {
+        self.outputs
+            .iter()
+            .filter(|o| o.machine == machine)
+            .map(|o| o.value)
+            .collect()
+    }
+}
+
+/// A scheduled machine failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MachineError {
+    pub machine: usize,
+    pub error: IntcodeError,
+}
+
+impl fmt::Display for MachineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "machine {}: {}", self.machine, self.error)
+    }
+}
+
+impl Error for MachineError {}
+
+/// Runs several programs round-robin on the calling thread, feeding each
+/// machine's outputs to the inputs of the machines it is connected to.
+///
+/// Each turn runs a machine until it blocks on input, halts or uses up its
+/// quantum, so a given network always interleaves the same way. Pausing
+/// watchpoints do not pause scheduled machines and their hits are dropped;
+/// callback watchpoints still run.
+#[derive(Default)]
+pub struct Scheduler {
+    machines: Vec<Program>,
+    routes: Vec<Vec<usize>>,
+    quantum: Option<u64>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler::default()
+    }
+
+    /// Adds a machine and returns its index. Inputs already pushed onto the
+    /// program, such as phase settings, are consumed first.
+    pub fn add_machine(&mut self, program: Program) -> usize {
+        self.machines.push(program);
+        self.routes.push(Vec::new());
+        self.machines.len() - 1
+    }
+
+    /// Sends every output of `from` to the input of `to`, in addition to any
+    /// other machines `from` is already connected to.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        self.routes[from].push(to);
+    }
+
+    /// Limits how many instructions a machine runs per turn.
+    pub fn set_quantum(&mut self, quantum: u64) {
+        self.quantum = Some(quantum);
+    }
+
+    pub fn machine(&self, machine: usize) -> &Program {
+        &self.machines[machine]
+    }
+
+    pub fn machine_mut(&mut self, machine: usize) -> &mut Program {
+        &mut self.machines[machine]
+    }
+
+    pub fn len(&self) -> usize {
+        self.machines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.machines.is_empty()
+    }
+
+    /// Runs until every machine has halted or no machine can make progress.
+    pub fn run(&mut self) -> Result<Report, MachineError> {
+        let mut outputs = Vec::new();
+        loop {
+            let mut progress = false;
+            for machine in 0..self.machines.len() {
+                if !self.machines[machine].is_halted() {
+                    progress |= self.turn(machine, &mut outputs)?;
+                }
+            }
+            let running = (0..self.machines.len())
+                .filter(|&m| !self.machines[m].is_halted())
+                .collect::<Vec<usize>>();
+            let outcome = if running.is_empty() {
+                Outcome::Halted
+            } else if !progress {
+                Outcome::Deadlock { blocked: running }
+            } else {
+                continue;
+            };
+            return Ok(Report { outcome, outputs });
+        }
+    }
+
+    /// Runs one turn of `machine`, returning whether it executed anything.
+    fn turn(&mut self, machine: usize, outputs: &mut Vec<Output>) -> Result<bool, MachineError> {
+        let executed = self.machines[machine].executed();
+        loop {
+            let program = &mut self.machines[machine];
+            if self.quantum.is_some_and(|q| program.executed() - executed >= q) {
+                break;
+            }
+            let step = program.step().map_err(|error| MachineError { machine, error })?;
+            program.clear_watch_hits();
+            match step.event {
+                Some(Event::Output(value)) => {
+                    outputs.push(Output { machine, value });
+                    for &to in &self.routes[machine] {
+                        self.machines[to].push_input(value);
+                    }
+                }
+                Some(Event::NeedInput) | Some(Event::Halted) => break,
+                _ => (),
+            }
+        }
+        Ok(self.machines[machine].executed() > executed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Echoes one input and halts: `IN -> [10]; OUT [10]; HLT`.
+    fn echo_once() -> Program {
+        Program::new(vec![3, 10, 4, 10, 99], None)
+    }
+
+    fn values(report: &Report) -> Vec<(usize, Word)> {
+        report.outputs.iter().map(|o| (o.machine, o.value)).collect()
+    }
+
+    #[test]
+    fn chained_machines_pass_outputs_along() {
+        let mut scheduler = Scheduler::new();
+        let mut first = echo_once();
+        first.push_input(5);
+        let first = scheduler.add_machine(first);
+        let second = scheduler.add_machine(echo_once());
+        scheduler.connect(first, second);
+        let report = scheduler.run().unwrap();
+        assert_eq!(report.outcome, Outcome::Halted);
+        assert_eq!(report.outputs_of(second), vec![5]);
+        assert_eq!(
+            report.final_output(),
+            Some(Output {
+                machine: second,
+                value: 5
+            })
+        );
+    }
+
+    #[test]
+    fn a_ring_with_no_inputs_deadlocks() {
+        let mut scheduler = Scheduler::new();
+        let first = scheduler.add_machine(echo_once());
+        let second = scheduler.add_machine(echo_once());
+        scheduler.connect(first, second);
+        scheduler.connect(second, first);
+        let report = scheduler.run().unwrap();
+        assert_eq!(report.outcome, Outcome::Deadlock { blocked: vec![0, 1] });
+        assert_eq!(report.final_output(), None);
+    }
+
+    #[test]
+    fn the_quantum_interleaves_machines() {
+        let mut scheduler = Scheduler::new();
+        scheduler.add_machine(Program::new(vec![104, 1, 104, 2, 99], None));
+        scheduler.add_machine(Program::new(vec![104, 3, 104, 4, 99], None));
+        let mut interleaved = Scheduler::new();
+        interleaved.add_machine(Program::new(vec![104, 1, 104, 2, 99], None));
+        interleaved.add_machine(Program::new(vec![104, 3, 104, 4, 99], None));
+        interleaved.set_quantum(1);
+
+        let report = scheduler.run().unwrap();
+        assert_eq!(values(&report), vec![(0, 1), (0, 2), (1, 3), (1, 4)]);
+        let report = interleaved.run().unwrap();
+        assert_eq!(values(&report), vec![(0, 1), (1, 3), (0, 2), (1, 4)]);
+        assert_eq!(report.outcome, Outcome::Halted);
+    }
+
+    #[test]
+    fn errors_name_the_failing_machine() {
+        let mut scheduler = Scheduler::new();
+        scheduler.add_machine(Program::new(vec![104, 1, 99], None));
+        scheduler.add_machine(Program::new(vec![98], None));
+        let error = scheduler.run().unwrap_err();
+        assert_eq!(
+            error,
+            MachineError {
+                machine: 1,
+                error: IntcodeError::InvalidOpCode {
+                    address: 0,
+                    code: 98
+                }
+            }
+        );
+        assert_eq!(error.to_string(), "machine 1: invalid op code 98 at 0");
+    }
+}
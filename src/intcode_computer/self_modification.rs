@@ -0,0 +1,174 @@
+use super::{Instruction, IntcodeError, Word};
+use log::warn;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// How `Program::enable_self_modification_check` reacts to writes into code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Logs a warning and lets the write happen.
+    Warn,
+    /// Fails the instruction with `IntcodeError::SelfModifyingCode` before writing.
+    Strict,
+}
+
+/// Which code a self-modifying write landed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// A cell of an instruction that has already executed, including the
+    /// instruction doing the write.
+    Executed,
+    /// A cell of the instruction the pointer moves to next.
+    Next,
+}
+
+/// A write into code, made by the instruction at `instruction_ptr`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfModification {
+    pub instruction_ptr: usize,
+    pub address: usize,
+    pub old: Word,
+    pub new: Word,
+    pub target: Target,
+}
+
+/// Cells executed as code so far and every write that landed in code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelfModifications {
+    mode: Mode,
+    code: Vec<bool>,
+    pub records: Vec<SelfModification>,
+}
+
+impl SelfModifications {
+    pub fn new(mode: Mode) -> Self {
+        SelfModifications {
+            mode,
+            code: Vec::new(),
+            records: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Whether the cell at `address` has been executed as part of an instruction.
+    pub fn is_code(&self, address: usize) -> bool {
+        self.code.get(address).copied().unwrap_or(false)
+    }
+
+    /// Marks the cells of an instruction about to execute as code.
+    pub(crate) fn mark(&mut self, instruction: &Instruction) {
+        let end = instruction.address + 1 + instruction.param_modes().len();
+        if end > self.code.len() {
+            self.code.resize(end, false);
+        }
+        for cell in &mut self.code[instruction.address..end] {
+            *cell = true;
+        }
+    }
+
+    /// Classifies a write of `new` over `old` at `address`, made by the
+    /// instruction at `instruction_ptr` with the pointer now at `next`, where
+    /// the next instruction occupies `next_len` cells.
+    pub(crate) fn check(
+        &mut self,
+        instruction_ptr: usize,
+        next: usize,
+        next_len: usize,
+        address: usize,
+        old: Word,
+        new: Word,
+    ) -> Result<(), IntcodeError> {
+        let target = if self.is_code(address) {
+            Target::Executed
+        } else if address >= next && address < next + next_len {
+            Target::Next
+        } else {
+            return Ok(());
+        };
+        self.records.push(SelfModification {
+            instruction_ptr,
+            address,
+            old,
+            new,
+            target,
+        });
+        warn!(
+            "instruction at {} overwrote code at {} ({} -> {})",
+            instruction_ptr, address, old, new
+        );
+        match self.mode {
+            Mode::Warn => Ok(()),
+            Mode::Strict => Err(IntcodeError::SelfModifyingCode {
+                address: instruction_ptr,
+                target: address,
+            }),
+        }
+    }
+
+    /// Distinct (writer, written address) pairs with the kind of code first
+    /// overwritten there and how often the write occurred.
+    pub fn sites(&self) -> BTreeMap<(usize, usize), (Target, usize)> {
+        let mut sites = BTreeMap::new();
+        for record in &self.records {
+            let site = sites
+                .entry((record.instruction_ptr, record.address))
+                .or_insert((record.target, 0));
+            site.1 += 1;
+        }
+        sites
+    }
+}
+
+/// Renders one line per site, e.g. `0004 -> 0000 executed x1`.
+impl fmt::Display for SelfModifications {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "self-modifying writes: {}", self.records.len())?;
+        for ((instruction_ptr, address), (target, count)) in self.sites() {
+            let target = match target {
+                Target::Executed => "executed",
+                Target::Next => "next",
+            };
+            writeln!(f, "  {:04} -> {:04} {} x{}", instruction_ptr, address, target, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Action, Program};
+    use super::*;
+
+    #[test]
+    fn strict_mode_leaves_the_pointer_on_the_writer() {
+        // ADD #5, #6 -> [4]; HLT
+        let mut program = Program::new(vec![1101, 5, 6, 4, 99], None);
+        program.enable_self_modification_check(Mode::Strict);
+        assert_eq!(
+            program.step().map(|s| s.event),
+            Err(IntcodeError::SelfModifyingCode {
+                address: 0,
+                target: 4
+            })
+        );
+        assert_eq!(program.instruction_ptr(), 0);
+        assert_eq!(program.instructions[4], 99);
+        let records = &program.self_modifications().unwrap().records;
+        assert_eq!(records[0].target, Target::Next);
+    }
+
+    #[test]
+    fn writes_into_the_next_custom_instruction_are_detected() {
+        // ADD #1, #1 -> [7]; EXT50 [0], [0], [0]; HLT
+        let mut program = Program::new(vec![1101, 1, 1, 7, 50, 0, 0, 0, 99], None);
+        program.register_op_code(50, 3, Box::new(|_, _| Ok(Action::Continue))).unwrap();
+        program.enable_self_modification_check(Mode::Warn);
+        program.step().unwrap();
+        let records = &program.self_modifications().unwrap().records;
+        assert_eq!(records.len(), 1);
+        assert_eq!((records[0].address, records[0].target), (7, Target::Next));
+    }
+}
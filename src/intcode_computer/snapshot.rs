@@ -0,0 +1,146 @@
+use super::Word;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+/// Complete machine state of a `Program`, apart from its attached `IO`.
+///
+/// Taken with `Program::snapshot` and turned back into a running machine with
+/// `Program::restore`, so an expensive prefix of a run can be computed once
+/// and forked many times.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub memory: Vec<Word>,
+    pub instruction_ptr: usize,
+    pub relative_base: Word,
+    pub memory_limit: usize,
+    /// Inputs queued with `Program::push_input` but not yet consumed.
+    pub inputs: Vec<Word>,
+    pub halted: bool,
+}
+
+impl Snapshot {
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+fn join(words: &[Word]) -> String {
+    words
+        .iter()
+        .map(|w| w.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Writes one `key=value` line per field, with lists comma separated.
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "ip={}", self.instruction_ptr)?;
+        writeln!(f, "rb={}", self.relative_base)?;
+        writeln!(f, "limit={}", self.memory_limit)?;
+        writeln!(f, "halted={}", self.halted)?;
+        writeln!(f, "inputs={}", join(&self.inputs))?;
+        writeln!(f, "memory={}", join(&self.memory))
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut snapshot = Snapshot {
+            memory: Vec::new(),
+            instruction_ptr: 0,
+            relative_base: 0,
+            memory_limit: super::DEFAULT_MEMORY_LIMIT,
+            inputs: Vec::new(),
+            halted: false,
+        };
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let invalid = || format!("invalid snapshot line '{}'", line);
+            let mut parts = line.trim().splitn(2, '=');
+            let key = parts.next().unwrap();
+            let value = parts.next().ok_or_else(invalid)?;
+            let words = || {
+                value
+                    .split(',')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.parse::<Word>().map_err(|_| invalid()))
+                    .collect::<Result<Vec<Word>, String>>()
+            };
+            match key {
+                "ip" => snapshot.instruction_ptr = value.parse().map_err(|_| invalid())?,
+                "rb" => snapshot.relative_base = value.parse().map_err(|_| invalid())?,
+                "limit" => snapshot.memory_limit = value.parse().map_err(|_| invalid())?,
+                "halted" => snapshot.halted = value.parse().map_err(|_| invalid())?,
+                "inputs" => snapshot.inputs = words()?,
+                "memory" => snapshot.memory = words()?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intcode_computer::{Event, Program};
+    use std::env;
+
+    /// Adds pairs of inputs: `IN -> [rb+20]; IN -> [rb+21]; ADD ... -> [rb+22];
+    /// OUT [rb+22]; ARB #3; JT #1, #0`.
+    fn adder() -> Program {
+        let image = vec![
+            203, 20, 203, 21, 22201, 20, 21, 22, 204, 22, 109, 3, 1105, 1, 0,
+        ];
+        Program::new(image, None)
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let snapshot = Snapshot {
+            memory: vec![1, -2, 3],
+            instruction_ptr: 2,
+            relative_base: -7,
+            memory_limit: 64,
+            inputs: vec![],
+            halted: true,
+        };
+        assert_eq!(snapshot.to_string().parse::<Snapshot>(), Ok(snapshot));
+        assert!("ip=x".parse::<Snapshot>().is_err());
+        assert!("memory=1,2\nbogus=1".parse::<Snapshot>().is_err());
+    }
+
+    #[test]
+    fn save_load_and_restore_resume_the_run() {
+        let mut program = adder();
+        program.push_input(2);
+        program.push_input(3);
+        program.push_input(10);
+        assert_eq!(program.run_until_event(), Ok(Event::Output(5)));
+
+        let path = env::temp_dir().join(format!("intcode-snapshot-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        program.snapshot().save(path).unwrap();
+        let loaded = Snapshot::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded, program.snapshot());
+        assert_eq!(loaded.inputs, vec![10]);
+
+        for second in [1, 20] {
+            let mut fork = Program::restore(&loaded, None);
+            fork.push_input(second);
+            assert_eq!(fork.run_until_event(), Ok(Event::Output(10 + second)));
+            assert_eq!(fork.relative_base(), 3);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use super::scheduler::{MachineError, Report, Scheduler};
+use super::{Program, Word};
+
+struct Machine {
+    image: Vec<Word>,
+    inputs: Vec<Word>,
+}
+
+/// Outcome of running a `Topology`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkOutput {
+    pub report: Report,
+    /// Outputs of the machines marked with `Topology::collect`, in the order
+    /// they were produced.
+    pub values: Vec<Word>,
+}
+
+/// Declares a network of machines and the connections between them, then
+/// runs it on a `Scheduler`.
+///
+/// Machines are identified by the index `machine` returns. Connections copy
+/// every output of one machine to the input of another, so several edges from
+/// one machine fan its outputs out and several edges into one machine
+/// interleave their outputs into its input.
+#[derive(Default)]
+pub struct Topology {
+    machines: Vec<Machine>,
+    edges: Vec<(usize, usize)>,
+    collected: Vec<usize>,
+}
+
+impl Topology {
+    pub fn new() -> Self {
+        Topology::default()
+    }
+
+    /// Adds a machine running `image` that reads `inputs`, such as a phase
+    /// setting, before anything sent to it.
+    pub fn machine(&mut self, image: Vec<Word>, inputs: Vec<Word>) -> usize {
+        self.machines.push(Machine { image, inputs });
+        self.machines.len() - 1
+    }
+
+    pub fn edge(&mut self, from: usize, to: usize) -> &mut Self {
+        self.edges.push((from, to));
+        self
+    }
+
+    /// Connects each machine to the next one.
+    pub fn chain(&mut self, machines: &[usize]) -> &mut Self {
+        for pair in machines.windows(2) {
+            self.edge(pair[0], pair[1]);
+        }
+        self
+    }
+
+    /// Connects each machine to the next one and the last back to the first.
+    pub fn ring(&mut self, machines: &[usize]) -> &mut Self {
+        self.chain(machines);
+        if let (Some(&first), Some(&last)) = (machines.first(), machines.last()) {
+            self.edge(last, first);
+        }
+        self
+    }
+
+    pub fn fan_out(&mut self, from: usize, to: &[usize]) -> &mut Self {
+        for &machine in to {
+            self.edge(from, machine);
+        }
+        self
+    }
+
+    pub fn fan_in(&mut self, from: &[usize], to: usize) -> &mut Self {
+        for &machine in from {
+            self.edge(machine, to);
+        }
+        self
+    }
+
+    /// Marks the outputs of `machine` as results of the network.
+    pub fn collect(&mut self, machine: usize) -> &mut Self {
+        self.collected.push(machine);
+        self
+    }
+
+    /// Creates the machines and their connections on a new scheduler.
+    pub fn build(&self) -> Scheduler {
+        let mut scheduler = Scheduler::new();
+        for machine in &self.machines {
+            let mut program = Program::new(machine.image.clone(), None);
+            for &input in &machine.inputs {
+                program.push_input(input);
+            }
+            scheduler.add_machine(program);
+        }
+        for &(from, to) in &self.edges {
+            scheduler.connect(from, to);
+        }
+        scheduler
+    }
+
+    /// Runs the network until it halts or deadlocks.
+    pub fn run(&self) -> Result<NetworkOutput, MachineError> {
+        let report = self.build().run()?;
+        let values = report
+            .outputs
+            .iter()
+            .filter(|o| self.collected.contains(&o.machine))
+            .map(|o| o.value)
+            .collect();
+        Ok(NetworkOutput { report, values })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::scheduler::Outcome;
+    use super::*;
+
+    /// Adds one day 7 amplifier per phase, the first also reading the signal 0.
+    fn amplifiers(topology: &mut Topology, image: &[Word], phases: &[Word]) -> Vec<usize> {
+        phases
+            .iter()
+            .enumerate()
+            .map(|(i, &phase)| {
+                let inputs = if i == 0 { vec![phase, 0] } else { vec![phase] };
+                topology.machine(image.to_vec(), inputs)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_chain_of_amplifiers() {
+        let image = vec![3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0];
+        let mut topology = Topology::new();
+        let machines = amplifiers(&mut topology, &image, &[4, 3, 2, 1, 0]);
+        topology.chain(&machines).collect(machines[4]);
+        let output = topology.run().unwrap();
+        assert_eq!(output.report.outcome, Outcome::Halted);
+        assert_eq!(output.values, vec![43210]);
+    }
+
+    #[test]
+    fn a_ring_of_amplifiers_with_feedback() {
+        let image = vec![
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let mut topology = Topology::new();
+        let machines = amplifiers(&mut topology, &image, &[9, 8, 7, 6, 5]);
+        topology.ring(&machines).collect(machines[4]);
+        let output = topology.run().unwrap();
+        assert_eq!(output.report.outcome, Outcome::Halted);
+        assert_eq!(output.values.last(), Some(&139629729));
+        assert_eq!(output.values.len(), 5);
+    }
+
+    #[test]
+    fn fan_out_and_fan_in() {
+        // Doubles its input: `IN -> [9]; MUL [9], #2 -> [9]; OUT [9]; HLT`.
+        let double = vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0];
+        // Adds two inputs: `IN -> [11]; IN -> [12]; ADD [11], [12] -> [11]; OUT [11]; HLT`.
+        let sum = vec![3, 11, 3, 12, 1, 11, 12, 11, 4, 11, 99, 0, 0];
+        let mut topology = Topology::new();
+        let source = topology.machine(vec![104, 3, 99], Vec::new());
+        let left = topology.machine(double.clone(), Vec::new());
+        let right = topology.machine(double, Vec::new());
+        let sink = topology.machine(sum, Vec::new());
+        topology
+            .fan_out(source, &[left, right])
+            .fan_in(&[left, right], sink)
+            .collect(left)
+            .collect(sink);
+        let output = topology.run().unwrap();
+        assert_eq!(output.values, vec![6, 12]);
+        assert_eq!(output.report.outputs_of(right), vec![6]);
+    }
+}
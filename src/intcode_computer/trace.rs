@@ -0,0 +1,273 @@
+use super::{Event, IntcodeError, OpCodes, Program, Step, Word, Write};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+/// What one executed instruction did, as kept in a `Trace`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub address: usize,
+    pub code: OpCodes,
+    pub operands: Vec<Word>,
+    pub write: Option<Write>,
+    pub input: Option<Word>,
+    pub output: Option<Word>,
+}
+
+impl TraceRecord {
+    pub fn new(step: &Step) -> Self {
+        TraceRecord {
+            address: step.instruction.address,
+            code: step.instruction.code,
+            operands: step.operands().to_vec(),
+            write: step.write,
+            input: step.input,
+            output: step.output(),
+        }
+    }
+}
+
+/// Formats a record as a single line, e.g. `0012 ADD 5,3,101 w=101:0:8 in=7 out=8`.
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operands = if self.operands.is_empty() {
+            String::from("-")
+        } else {
+            self.operands
+                .iter()
+                .map(|o| o.to_string())
+                .collect::<Vec<String>>()
+                .join(",")
+        };
+        write!(f, "{:04} {} {}", self.address, self.code.name(), operands)?;
+        if let Some(write) = self.write {
+            write!(f, " w={}:{}:{}", write.address, write.old, write.new)?;
+        }
+        if let Some(input) = self.input {
+            write!(f, " in={}", input)?;
+        }
+        if let Some(output) = self.output {
+            write!(f, " out={}", output)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TraceRecord {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid trace record '{}'", line);
+        let number = |s: &str| s.parse::<Word>().map_err(|_| invalid());
+        let mut fields = line.split_whitespace();
+        let address = fields
+            .next()
+            .and_then(|s| s.parse::<usize>().ok())
+            .ok_or_else(invalid)?;
+        let name = fields.next().ok_or_else(invalid)?;
+        let operands = match fields.next().ok_or_else(invalid)? {
+            "-" => Vec::new(),
+            list => list.split(',').map(number).collect::<Result<Vec<Word>, String>>()?,
+        };
+        let code = OpCodes::from_name(name, operands.len()).ok_or_else(invalid)?;
+        let mut record = TraceRecord {
+            address,
+            code,
+            operands,
+            write: None,
+            input: None,
+            output: None,
+        };
+        for field in fields {
+            if let Some(write) = field.strip_prefix("w=") {
+                let parts = write.split(':').map(number).collect::<Result<Vec<Word>, String>>()?;
+                if parts.len() != 3 || parts[0] < 0 {
+                    return Err(invalid());
+                }
+                record.write = Some(Write {
+                    address: parts[0] as usize,
+                    old: parts[1],
+                    new: parts[2],
+                });
+            } else if let Some(input) = field.strip_prefix("in=") {
+                record.input = Some(number(input)?);
+            } else if let Some(output) = field.strip_prefix("out=") {
+                record.output = Some(number(output)?);
+            } else {
+                return Err(invalid());
+            }
+        }
+        Ok(record)
+    }
+}
+
+/// Structured log of every instruction a program executed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub records: Vec<TraceRecord>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Trace::default()
+    }
+
+    pub fn record(&mut self, step: &Step) {
+        self.records.push(TraceRecord::new(step));
+    }
+
+    /// Inputs consumed during the traced run, in order.
+    pub fn inputs(&self) -> Vec<Word> {
+        self.records.iter().filter_map(|r| r.input).collect()
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut text = self
+            .records
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<String>>()
+            .join("\n");
+        text.push('\n');
+        fs::write(path, text)
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        let records = fs::read_to_string(path)?
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| l.parse::<TraceRecord>())
+            .collect::<Result<Vec<TraceRecord>, String>>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Trace { records })
+    }
+
+    /// Index of the first record where the two traces differ, if any.
+    pub fn diverges_at(&self, other: &Trace) -> Option<usize> {
+        let common = self.records.len().min(other.records.len());
+        (0..common)
+            .find(|&i| self.records[i] != other.records[i])
+            .or(if self.records.len() == other.records.len() {
+                None
+            } else {
+                Some(common)
+            })
+    }
+
+    /// Runs `program` against this trace, feeding it the recorded inputs, and
+    /// returns the index of the first instruction that behaves differently.
+    ///
+    /// Only the recorded instructions are executed, so a trace cut short at any
+    /// event replays cleanly and leaves the program where the trace ended.
+    /// Pausing watchpoint hits during the replay are dropped.
+    pub fn replay(&self, program: &mut Program) -> Result<Option<usize>, IntcodeError> {
+        for input in self.inputs() {
+            program.push_input(input);
+        }
+        for (index, expected) in self.records.iter().enumerate() {
+            let step = program.step()?;
+            program.clear_watch_hits();
+            if step.event == Some(Event::NeedInput) || TraceRecord::new(&step) != *expected {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Action;
+    use super::*;
+    use std::env;
+
+    /// Echoes inputs until it reads 0: `IN -> [20]; OUT [20]; JT [20], #0; HLT`.
+    fn echo() -> Vec<Word> {
+        vec![3, 20, 4, 20, 1005, 20, 0, 99]
+    }
+
+    fn traced_run(image: Vec<Word>, inputs: &[Word]) -> Trace {
+        let mut program = Program::new(image, None);
+        program.enable_trace();
+        for &input in inputs {
+            program.push_input(input);
+        }
+        while program.run_until_event().unwrap() != Event::Halted {}
+        program.take_trace().unwrap()
+    }
+
+    #[test]
+    fn record_text_round_trips() {
+        let line = "0012 ADD 5,-3,101 w=101:0:2 in=7 out=8";
+        assert_eq!(line.parse::<TraceRecord>().unwrap().to_string(), line);
+        assert_eq!("0011 HLT -".parse::<TraceRecord>().unwrap().to_string(), "0011 HLT -");
+        assert!("0012 ADD 5,x".parse::<TraceRecord>().is_err());
+        assert!("0012 FOO -".parse::<TraceRecord>().is_err());
+        assert!("0012 EXT2 -".parse::<TraceRecord>().is_err());
+        assert!("0012 OUT 1 w=1:2".parse::<TraceRecord>().is_err());
+    }
+
+    #[test]
+    fn custom_op_codes_round_trip() {
+        // EXT50 #7, [6] writes 7 to [6]; HLT
+        let mut program = Program::new(vec![10150, 7, 6, 99, 0, 0, 0], None);
+        program
+            .register_op_code(
+                50,
+                2,
+                Box::new(|program, modes| {
+                    let value = program.parameter(modes[0])?;
+                    let location = program.location(modes[1])?;
+                    program.store(location, value)?;
+                    Ok(Action::Continue)
+                }),
+            )
+            .unwrap();
+        program.enable_trace();
+        while program.run_until_event().unwrap() != Event::Halted {}
+        let trace = program.take_trace().unwrap();
+        let line = trace.records[0].to_string();
+        assert_eq!(line, "0000 EXT50 7,6 w=6:0:7");
+        assert_eq!(line.parse::<TraceRecord>().unwrap(), trace.records[0]);
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let trace = traced_run(echo(), &[5, -9, 0]);
+        let path = env::temp_dir().join(format!("intcode-trace-{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        trace.save(path).unwrap();
+        let loaded = Trace::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded, trace);
+        assert_eq!(loaded.inputs(), vec![5, -9, 0]);
+    }
+
+    #[test]
+    fn replay_matches_a_trace_stopped_at_an_output() {
+        let mut program = Program::new(echo(), None);
+        program.enable_trace();
+        program.push_input(5);
+        assert_eq!(program.run_until_event(), Ok(Event::Output(5)));
+        let trace = program.take_trace().unwrap();
+
+        let mut replayed = Program::new(echo(), None);
+        assert_eq!(trace.replay(&mut replayed), Ok(None));
+        assert_eq!(replayed.executed(), 2);
+        assert_eq!(replayed.instruction_ptr(), 4);
+    }
+
+    #[test]
+    fn replay_and_diff_report_the_first_divergence() {
+        let trace = traced_run(echo(), &[5, 6, 0]);
+        let mut patched = echo();
+        patched[5] = 21;
+        let mut program = Program::new(patched.clone(), None);
+        assert_eq!(trace.replay(&mut program), Ok(Some(2)));
+
+        let other = traced_run(patched, &[5, 6, 0]);
+        assert_eq!(trace.diverges_at(&other), Some(2));
+        assert_eq!(trace.diverges_at(&trace.clone()), None);
+    }
+}
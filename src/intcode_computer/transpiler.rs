@@ -0,0 +1,174 @@
+use super::disassembler;
+use super::{Instruction, OpCodes, Word};
+use std::fmt::Write;
+
+/// Translates a program image into Rust source implementing the same
+/// behaviour against the `IO` trait.
+///
+/// The generated module exposes `pub fn run(io: Box<dyn IO>) -> Result<(), IntcodeError>`.
+/// Code reachable from address 0 is compiled to one match arm per instruction,
+/// with its operands inlined. Control reaching any other address, and any write
+/// into the cells of a compiled instruction, hands the current machine state to
+/// the interpreter, which finishes the run.
+pub fn transpile(image: &[Word]) -> String {
+    let code = disassembler::reachable(image, 0);
+    let mut is_code = vec![false; image.len()];
+    for instruction in code.values() {
+        let end = instruction.address + 1 + instruction.param_modes().len();
+        for cell in is_code.iter_mut().take(end).skip(instruction.address) {
+            *cell = true;
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str(HEADER);
+    writeln!(out, "const IMAGE: &[Word] = &{:?};\n", image).unwrap();
+    writeln!(out, "const CODE: &[bool] = &{:?};\n", is_code).unwrap();
+    out.push_str(HELPERS);
+    out.push_str(RUN_START);
+    for instruction in code.values() {
+        emit_instruction(&mut out, instruction, image);
+    }
+    out.push_str(RUN_END);
+    out
+}
+
+fn emit_instruction(out: &mut String, instruction: &Instruction, image: &[Word]) {
+    let address = instruction.address;
+    let modes = instruction.param_modes();
+    let next = address + 1 + modes.len();
+    let parameter = |i: usize| image[address + 1 + i];
+    let read = |i: usize| match modes[i] {
+        0 => format!("load(&mem, {}, {})?", parameter(i), address),
+        1 => format!("{}", parameter(i)),
+        _ => format!("load(&mem, relative(rb, {}, {})?, {})?", parameter(i), address, address),
+    };
+    let store = |value: String| {
+        let target = match modes[modes.len() - 1] {
+            0 => format!("{}", parameter(modes.len() - 1)),
+            _ => format!("relative(rb, {}, {})?", parameter(modes.len() - 1), address),
+        };
+        vec![
+            format!("let value = {};", value),
+            format!("if store(&mut mem, {}, value, {})? {{", target, address),
+            format!("    return fallback(mem, {}, rb, io);", next),
+            String::from("}"),
+            format!("ip = {};", next),
+        ]
+    };
+
+    let checked = |operation: &str, a: String, b: String| {
+        let overflow = format!("IntcodeError::Overflow {{ address: {} }}", address);
+        format!("Word::{}({}, {}).ok_or({})?", operation, a, b, overflow)
+    };
+    let body = match instruction.code {
+        OpCodes::Add => store(checked("checked_add", read(0), read(1))),
+        OpCodes::Multiply => store(checked("checked_mul", read(0), read(1))),
+        OpCodes::LessThan => store(format!("({} < {}) as Word", read(0), read(1))),
+        OpCodes::Equals => store(format!("({} == {}) as Word", read(0), read(1))),
+        OpCodes::Input => store(String::from("io.read().ok_or(IntcodeError::InputExhausted)?")),
+        OpCodes::Output => vec![format!("io.write({});", read(0)), format!("ip = {};", next)],
+        OpCodes::JumpIfTrue | OpCodes::JumpIfFalse => {
+            let comparison = if instruction.code == OpCodes::JumpIfTrue {
+                "!="
+            } else {
+                "=="
+            };
+            vec![
+                format!("ip = if {} {} 0 {{", read(0), comparison),
+                format!("    address({}, {})?", read(1), address),
+                String::from("} else {"),
+                format!("    {}", next),
+                String::from("};"),
+            ]
+        }
+        OpCodes::AdjustRelativeBase => {
+            vec![
+                format!("rb = {};", checked("checked_add", String::from("rb"), read(0))),
+                format!("ip = {};", next),
+            ]
+        }
+        OpCodes::Halt => vec![String::from("return Ok(());")],
+        OpCodes::Custom { .. } => vec![format!("return fallback(mem, {}, rb, io);", address)],
+    };
+
+    writeln!(out, "            {} => {{", address).unwrap();
+    for line in body {
+        writeln!(out, "                {}", line).unwrap();
+    }
+    writeln!(out, "            }}").unwrap();
+}
+
+const HEADER: &str = "// Generated by the advent_of_code_2019 Intcode transpiler.
+
+use advent_of_code_2019::intcode_computer::snapshot::Snapshot;
+use advent_of_code_2019::intcode_computer::{self, IntcodeError, Program, Word, IO};
+
+";
+
+const HELPERS: &str = "#[allow(dead_code)]
+fn address(target: Word, at: usize) -> Result<usize, IntcodeError> {
+    if target < 0 {
+        return Err(IntcodeError::NegativeAddress { address: at, target });
+    }
+    let target = target as usize;
+    if target >= intcode_computer::DEFAULT_MEMORY_LIMIT {
+        return Err(IntcodeError::AddressOutOfRange {
+            target,
+            limit: intcode_computer::DEFAULT_MEMORY_LIMIT,
+        });
+    }
+    Ok(target)
+}
+
+#[allow(dead_code)]
+fn relative(rb: Word, offset: Word, at: usize) -> Result<Word, IntcodeError> {
+    rb.checked_add(offset).ok_or(IntcodeError::Overflow { address: at })
+}
+
+#[allow(dead_code)]
+fn load(mem: &[Word], target: Word, at: usize) -> Result<Word, IntcodeError> {
+    Ok(mem.get(address(target, at)?).copied().unwrap_or(0))
+}
+
+/// Writes `value` and reports whether it landed in compiled code.
+#[allow(dead_code)]
+fn store(mem: &mut Vec<Word>, target: Word, value: Word, at: usize) -> Result<bool, IntcodeError> {
+    let target = address(target, at)?;
+    if target >= mem.len() {
+        mem.resize(target + 1, 0);
+    }
+    mem[target] = value;
+    Ok(CODE.get(target).copied().unwrap_or(false))
+}
+
+#[allow(dead_code)]
+fn fallback(memory: Vec<Word>, ip: usize, rb: Word, io: Box<dyn IO>) -> Result<(), IntcodeError> {
+    let snapshot = Snapshot {
+        memory,
+        instruction_ptr: ip,
+        relative_base: rb,
+        memory_limit: intcode_computer::DEFAULT_MEMORY_LIMIT,
+        inputs: Vec::new(),
+        halted: false,
+    };
+    let mut program = Program::restore(&snapshot, Some(io));
+    intcode_computer::process(&mut program)
+}
+
+";
+
+const RUN_START: &str = "#[allow(unused_mut, clippy::all)]
+pub fn run(mut io: Box<dyn IO>) -> Result<(), IntcodeError> {
+    let mut mem = IMAGE.to_vec();
+    let mut ip: usize = 0;
+    let mut rb: Word = 0;
+    loop {
+        match ip {
+";
+
+const RUN_END: &str = "            _ => return fallback(mem, ip, rb, io),
+        }
+    }
+}
+";
@@ -0,0 +1,180 @@
+use super::Word;
+use std::ops::Range;
+
+/// Kind of memory access a watchpoint reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    fn covers(self, access: Access) -> bool {
+        self == Access::ReadWrite || self == access
+    }
+}
+
+/// A watched memory access. Reads report the value read as both `old` and `new`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub watchpoint: usize,
+    pub address: usize,
+    /// Address of the instruction that made the access.
+    pub instruction_ptr: usize,
+    pub access: Access,
+    pub old: Word,
+    pub new: Word,
+}
+
+/// What happens when a watchpoint is hit.
+pub enum WatchAction {
+    /// Pauses `Program::run_until_event` with `Event::Watchpoint` once the
+    /// instruction making the access has finished.
+    Pause,
+    Callback(Box<dyn FnMut(&WatchHit)>),
+}
+
+pub struct Watchpoint {
+    pub range: Range<usize>,
+    pub access: Access,
+    pub action: WatchAction,
+}
+
+/// Watchpoints registered on a program, indexed by registration order.
+#[derive(Default)]
+pub(crate) struct Watchpoints {
+    watchpoints: Vec<Option<Watchpoint>>,
+}
+
+impl Watchpoints {
+    pub(crate) fn add(&mut self, watchpoint: Watchpoint) -> usize {
+        self.watchpoints.push(Some(watchpoint));
+        self.watchpoints.len() - 1
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) {
+        if let Some(watchpoint) = self.watchpoints.get_mut(index) {
+            *watchpoint = None;
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.watchpoints.iter().all(Option::is_none)
+    }
+
+    /// Fires every watchpoint covering the access, returning the hits that
+    /// should pause execution.
+    pub(crate) fn check(
+        &mut self,
+        address: usize,
+        instruction_ptr: usize,
+        access: Access,
+        old: Word,
+        new: Word,
+    ) -> Vec<WatchHit> {
+        let mut pauses = Vec::new();
+        for (index, watchpoint) in self.watchpoints.iter_mut().enumerate() {
+            let watchpoint = match watchpoint {
+                Some(w) if w.range.contains(&address) && w.access.covers(access) => w,
+                _ => continue,
+            };
+            let hit = WatchHit {
+                watchpoint: index,
+                address,
+                instruction_ptr,
+                access,
+                old,
+                new,
+            };
+            match &mut watchpoint.action {
+                WatchAction::Pause => pauses.push(hit),
+                WatchAction::Callback(callback) => callback(&hit),
+            }
+        }
+        pauses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::scheduler::Scheduler;
+    use super::super::{process, Event, Program};
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// `ADD [10], #5 -> [11]; OUT [11]; HLT` with 7 at [10].
+    fn adder() -> Program {
+        Program::new(vec![1001, 10, 5, 11, 4, 11, 99, 0, 0, 0, 7, 0], None)
+    }
+
+    /// A hit of the first watchpoint by the instruction at `ip`.
+    fn hit(address: usize, ip: usize, access: Access, old: Word, new: Word) -> WatchHit {
+        WatchHit {
+            watchpoint: 0,
+            address,
+            instruction_ptr: ip,
+            access,
+            old,
+            new,
+        }
+    }
+
+    #[test]
+    fn pausing_watchpoints_come_before_the_event_of_their_instruction() {
+        let mut program = adder();
+        program.watch(11..12, Access::ReadWrite, WatchAction::Pause);
+        let events = [
+            Event::Watchpoint(hit(11, 0, Access::Write, 0, 12)),
+            Event::Watchpoint(hit(11, 4, Access::Read, 12, 12)),
+            Event::Output(12),
+            Event::Halted,
+        ];
+        for event in events {
+            assert_eq!(program.run_until_event(), Ok(event));
+        }
+    }
+
+    #[test]
+    fn callbacks_see_every_access_in_their_range() {
+        let mut program = adder();
+        let hits = Rc::new(RefCell::new(Vec::new()));
+        let log = hits.clone();
+        let callback = move |hit: &WatchHit| log.borrow_mut().push(*hit);
+        program.watch(10..12, Access::ReadWrite, WatchAction::Callback(Box::new(callback)));
+        let fetches = Rc::new(RefCell::new(0));
+        let count = fetches.clone();
+        let callback = move |_: &WatchHit| *count.borrow_mut() += 1;
+        program.watch(0..10, Access::Read, WatchAction::Callback(Box::new(callback)));
+
+        assert_eq!(program.run_until_event(), Ok(Event::Output(12)));
+        let expected = vec![
+            hit(10, 0, Access::Read, 7, 7),
+            hit(11, 0, Access::Write, 0, 12),
+            hit(11, 4, Access::Read, 12, 12),
+        ];
+        assert_eq!(*hits.borrow(), expected);
+        assert_eq!(*fetches.borrow(), 0);
+    }
+
+    #[test]
+    fn unwatched_watchpoints_no_longer_fire() {
+        let mut program = adder();
+        let watchpoint = program.watch(0..12, Access::ReadWrite, WatchAction::Pause);
+        program.unwatch(watchpoint);
+        assert_eq!(program.run_until_event(), Ok(Event::Output(12)));
+        assert_eq!(program.next_watch_hit(), None);
+        assert_eq!(process(&mut program), Ok(()));
+    }
+
+    #[test]
+    fn scheduled_machines_drop_their_pausing_hits() {
+        let mut program = adder();
+        program.watch(11..12, Access::Write, WatchAction::Pause);
+        let mut scheduler = Scheduler::new();
+        let machine = scheduler.add_machine(program);
+        assert_eq!(scheduler.run().unwrap().outputs_of(machine), vec![12]);
+        assert_eq!(scheduler.machine_mut(machine).next_watch_hit(), None);
+    }
+}
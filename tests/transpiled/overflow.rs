@@ -0,0 +1,88 @@
+// Generated by the advent_of_code_2019 Intcode transpiler.
+
+use advent_of_code_2019::intcode_computer::snapshot::Snapshot;
+use advent_of_code_2019::intcode_computer::{self, IntcodeError, Program, Word, IO};
+
+const IMAGE: &[Word] = &[1002, 9, 3, 9, 4, 9, 1105, 1, 0, 1000000000000000000];
+
+const CODE: &[bool] = &[true, true, true, true, true, true, true, true, true, false];
+
+#[allow(dead_code)]
+fn address(target: Word, at: usize) -> Result<usize, IntcodeError> {
+    if target < 0 {
+        return Err(IntcodeError::NegativeAddress { address: at, target });
+    }
+    let target = target as usize;
+    if target >= intcode_computer::DEFAULT_MEMORY_LIMIT {
+        return Err(IntcodeError::AddressOutOfRange {
+            target,
+            limit: intcode_computer::DEFAULT_MEMORY_LIMIT,
+        });
+    }
+    Ok(target)
+}
+
+#[allow(dead_code)]
+fn relative(rb: Word, offset: Word, at: usize) -> Result<Word, IntcodeError> {
+    rb.checked_add(offset).ok_or(IntcodeError::Overflow { address: at })
+}
+
+#[allow(dead_code)]
+fn load(mem: &[Word], target: Word, at: usize) -> Result<Word, IntcodeError> {
+    Ok(mem.get(address(target, at)?).copied().unwrap_or(0))
+}
+
+/// Writes `value` and reports whether it landed in compiled code.
+#[allow(dead_code)]
+fn store(mem: &mut Vec<Word>, target: Word, value: Word, at: usize) -> Result<bool, IntcodeError> {
+    let target = address(target, at)?;
+    if target >= mem.len() {
+        mem.resize(target + 1, 0);
+    }
+    mem[target] = value;
+    Ok(CODE.get(target).copied().unwrap_or(false))
+}
+
+#[allow(dead_code)]
+fn fallback(memory: Vec<Word>, ip: usize, rb: Word, io: Box<dyn IO>) -> Result<(), IntcodeError> {
+    let snapshot = Snapshot {
+        memory,
+        instruction_ptr: ip,
+        relative_base: rb,
+        memory_limit: intcode_computer::DEFAULT_MEMORY_LIMIT,
+        inputs: Vec::new(),
+        halted: false,
+    };
+    let mut program = Program::restore(&snapshot, Some(io));
+    intcode_computer::process(&mut program)
+}
+
+#[allow(unused_mut, clippy::all)]
+pub fn run(mut io: Box<dyn IO>) -> Result<(), IntcodeError> {
+    let mut mem = IMAGE.to_vec();
+    let mut ip: usize = 0;
+    let mut rb: Word = 0;
+    loop {
+        match ip {
+            0 => {
+                let value = Word::checked_mul(load(&mem, 9, 0)?, 3).ok_or(IntcodeError::Overflow { address: 0 })?;
+                if store(&mut mem, 9, value, 0)? {
+                    return fallback(mem, 4, rb, io);
+                }
+                ip = 4;
+            }
+            4 => {
+                io.write(load(&mem, 9, 4)?);
+                ip = 6;
+            }
+            6 => {
+                ip = if 1 != 0 {
+                    address(0, 6)?
+                } else {
+                    9
+                };
+            }
+            _ => return fallback(mem, ip, rb, io),
+        }
+    }
+}
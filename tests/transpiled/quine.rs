@@ -0,0 +1,102 @@
+// Generated by the advent_of_code_2019 Intcode transpiler.
+
+use advent_of_code_2019::intcode_computer::snapshot::Snapshot;
+use advent_of_code_2019::intcode_computer::{self, IntcodeError, Program, Word, IO};
+
+const IMAGE: &[Word] = &[109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+
+const CODE: &[bool] = &[true, true, true, true, true, true, true, true, true, true, true, true, true, true, true, true];
+
+#[allow(dead_code)]
+fn address(target: Word, at: usize) -> Result<usize, IntcodeError> {
+    if target < 0 {
+        return Err(IntcodeError::NegativeAddress { address: at, target });
+    }
+    let target = target as usize;
+    if target >= intcode_computer::DEFAULT_MEMORY_LIMIT {
+        return Err(IntcodeError::AddressOutOfRange {
+            target,
+            limit: intcode_computer::DEFAULT_MEMORY_LIMIT,
+        });
+    }
+    Ok(target)
+}
+
+#[allow(dead_code)]
+fn relative(rb: Word, offset: Word, at: usize) -> Result<Word, IntcodeError> {
+    rb.checked_add(offset).ok_or(IntcodeError::Overflow { address: at })
+}
+
+#[allow(dead_code)]
+fn load(mem: &[Word], target: Word, at: usize) -> Result<Word, IntcodeError> {
+    Ok(mem.get(address(target, at)?).copied().unwrap_or(0))
+}
+
+/// Writes `value` and reports whether it landed in compiled code.
+#[allow(dead_code)]
+fn store(mem: &mut Vec<Word>, target: Word, value: Word, at: usize) -> Result<bool, IntcodeError> {
+    let target = address(target, at)?;
+    if target >= mem.len() {
+        mem.resize(target + 1, 0);
+    }
+    mem[target] = value;
+    Ok(CODE.get(target).copied().unwrap_or(false))
+}
+
+#[allow(dead_code)]
+fn fallback(memory: Vec<Word>, ip: usize, rb: Word, io: Box<dyn IO>) -> Result<(), IntcodeError> {
+    let snapshot = Snapshot {
+        memory,
+        instruction_ptr: ip,
+        relative_base: rb,
+        memory_limit: intcode_computer::DEFAULT_MEMORY_LIMIT,
+        inputs: Vec::new(),
+        halted: false,
+    };
+    let mut program = Program::restore(&snapshot, Some(io));
+    intcode_computer::process(&mut program)
+}
+
+#[allow(unused_mut, clippy::all)]
+pub fn run(mut io: Box<dyn IO>) -> Result<(), IntcodeError> {
+    let mut mem = IMAGE.to_vec();
+    let mut ip: usize = 0;
+    let mut rb: Word = 0;
+    loop {
+        match ip {
+            0 => {
+                rb = Word::checked_add(rb, 1).ok_or(IntcodeError::Overflow { address: 0 })?;
+                ip = 2;
+            }
+            2 => {
+                io.write(load(&mem, relative(rb, -1, 2)?, 2)?);
+                ip = 4;
+            }
+            4 => {
+                let value = Word::checked_add(load(&mem, 100, 4)?, 1).ok_or(IntcodeError::Overflow { address: 4 })?;
+                if store(&mut mem, 100, value, 4)? {
+                    return fallback(mem, 8, rb, io);
+                }
+                ip = 8;
+            }
+            8 => {
+                let value = (load(&mem, 100, 8)? == 16) as Word;
+                if store(&mut mem, 101, value, 8)? {
+                    return fallback(mem, 12, rb, io);
+                }
+                ip = 12;
+            }
+            12 => {
+                ip = if load(&mem, 101, 12)? == 0 {
+                    address(0, 12)?
+                } else {
+                    15
+                };
+            }
+            15 => {
+                return Ok(());
+            }
+            _ => return fallback(mem, ip, rb, io),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// Generated by the advent_of_code_2019 Intcode transpiler.
+
+use advent_of_code_2019::intcode_computer::snapshot::Snapshot;
+use advent_of_code_2019::intcode_computer::{self, IntcodeError, Program, Word, IO};
+
+const IMAGE: &[Word] = &[109, 20, 203, 2, 21101, 3, 4, 3, 204, 2, 204, 3, 99];
+
+const CODE: &[bool] = &[true, true, true, true, true, true, true, true, true, true, true, true, true];
+
+#[allow(dead_code)]
+fn address(target: Word, at: usize) -> Result<usize, IntcodeError> {
+    if target < 0 {
+        return Err(IntcodeError::NegativeAddress { address: at, target });
+    }
+    let target = target as usize;
+    if target >= intcode_computer::DEFAULT_MEMORY_LIMIT {
+        return Err(IntcodeError::AddressOutOfRange {
+            target,
+            limit: intcode_computer::DEFAULT_MEMORY_LIMIT,
+        });
+    }
+    Ok(target)
+}
+
+#[allow(dead_code)]
+fn relative(rb: Word, offset: Word, at: usize) -> Result<Word, IntcodeError> {
+    rb.checked_add(offset).ok_or(IntcodeError::Overflow { address: at })
+}
+
+#[allow(dead_code)]
+fn load(mem: &[Word], target: Word, at: usize) -> Result<Word, IntcodeError> {
+    Ok(mem.get(address(target, at)?).copied().unwrap_or(0))
+}
+
+/// Writes `value` and reports whether it landed in compiled code.
+#[allow(dead_code)]
+fn store(mem: &mut Vec<Word>, target: Word, value: Word, at: usize) -> Result<bool, IntcodeError> {
+    let target = address(target, at)?;
+    if target >= mem.len() {
+        mem.resize(target + 1, 0);
+    }
+    mem[target] = value;
+    Ok(CODE.get(target).copied().unwrap_or(false))
+}
+
+#[allow(dead_code)]
+fn fallback(memory: Vec<Word>, ip: usize, rb: Word, io: Box<dyn IO>) -> Result<(), IntcodeError> {
+    let snapshot = Snapshot {
+        memory,
+        instruction_ptr: ip,
+        relative_base: rb,
+        memory_limit: intcode_computer::DEFAULT_MEMORY_LIMIT,
+        inputs: Vec::new(),
+        halted: false,
+    };
+    let mut program = Program::restore(&snapshot, Some(io));
+    intcode_computer::process(&mut program)
+}
+
+#[allow(unused_mut, clippy::all)]
+pub fn run(mut io: Box<dyn IO>) -> Result<(), IntcodeError> {
+    let mut mem = IMAGE.to_vec();
+    let mut ip: usize = 0;
+    let mut rb: Word = 0;
+    loop {
+        match ip {
+            0 => {
+                rb = Word::checked_add(rb, 20).ok_or(IntcodeError::Overflow { address: 0 })?;
+                ip = 2;
+            }
+            2 => {
+                let value = io.read().ok_or(IntcodeError::InputExhausted)?;
+                if store(&mut mem, relative(rb, 2, 2)?, value, 2)? {
+                    return fallback(mem, 4, rb, io);
+                }
+                ip = 4;
+            }
+            4 => {
+                let value = Word::checked_add(3, 4).ok_or(IntcodeError::Overflow { address: 4 })?;
+                if store(&mut mem, relative(rb, 3, 4)?, value, 4)? {
+                    return fallback(mem, 8, rb, io);
+                }
+                ip = 8;
+            }
+            8 => {
+                io.write(load(&mem, relative(rb, 2, 8)?, 8)?);
+                ip = 10;
+            }
+            10 => {
+                io.write(load(&mem, relative(rb, 3, 10)?, 10)?);
+                ip = 12;
+            }
+            12 => {
+                return Ok(());
+            }
+            _ => return fallback(mem, ip, rb, io),
+        }
+    }
+}
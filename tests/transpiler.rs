@@ -0,0 +1,64 @@
+//! Runs checked-in transpiler output against the interpreter. Each file under
+//! `tests/transpiled` must match what `transpile` currently generates.
+
+use advent_of_code_2019::intcode_computer::adapters::QueueIO;
+use advent_of_code_2019::intcode_computer::transpiler::transpile;
+use advent_of_code_2019::intcode_computer::{self, IntcodeError, Program, Word};
+
+#[path = "transpiled/overflow.rs"]
+mod overflow;
+#[path = "transpiled/quine.rs"]
+mod quine;
+#[path = "transpiled/relative.rs"]
+mod relative;
+#[path = "transpiled/self_modifying.rs"]
+mod self_modifying;
+
+type Run = fn(Box<dyn intcode_computer::IO>) -> Result<(), IntcodeError>;
+
+/// Checks `source` is up to date for `image`, then runs the compiled and the
+/// interpreted program on `inputs` and compares their results and outputs.
+fn compare(image: &[Word], source: &str, run: Run, inputs: &[Word]) -> Vec<Word> {
+    assert_eq!(transpile(image), source, "regenerate with the transpile binary");
+
+    let compiled = QueueIO::new(inputs.to_vec());
+    let compiled_result = run(Box::new(compiled.clone()));
+    let interpreted = QueueIO::new(inputs.to_vec());
+    let mut program = Program::new(image.to_vec(), Some(Box::new(interpreted.clone())));
+    let interpreted_result = intcode_computer::process(&mut program);
+
+    assert_eq!(compiled_result, interpreted_result);
+    assert_eq!(compiled.outputs(), interpreted.outputs());
+    compiled.outputs()
+}
+
+#[test]
+fn overflow_is_an_error_in_both() {
+    let image = [1002, 9, 3, 9, 4, 9, 1105, 1, 0, 1000000000000000000];
+    let source = include_str!("transpiled/overflow.rs");
+    let outputs = compare(&image, source, overflow::run, &[]);
+    assert_eq!(outputs, vec![3000000000000000000, 9000000000000000000]);
+    let result = overflow::run(Box::new(QueueIO::new(Vec::new())));
+    assert_eq!(result, Err(IntcodeError::Overflow { address: 0 }));
+}
+
+#[test]
+fn quine_outputs_itself() {
+    let image = [109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99];
+    let outputs = compare(&image, include_str!("transpiled/quine.rs"), quine::run, &[]);
+    assert_eq!(outputs, image);
+}
+
+#[test]
+fn relative_writes_and_input() {
+    let image = [109, 20, 203, 2, 21101, 3, 4, 3, 204, 2, 204, 3, 99];
+    let source = include_str!("transpiled/relative.rs");
+    assert_eq!(compare(&image, source, relative::run, &[42]), vec![42, 7]);
+}
+
+#[test]
+fn writes_into_code_fall_back_to_the_interpreter() {
+    let image = [1101, 4, 0, 5, 104, 7, 99];
+    let source = include_str!("transpiled/self_modifying.rs");
+    assert_eq!(compare(&image, source, self_modifying::run, &[]), vec![4]);
+}